@@ -0,0 +1,6 @@
+#![no_std]
+// code shared by the prediction contracts, kept free of soroban types so any
+// contract crate can depend on it
+
+pub mod payout;
+pub mod ttl;
@@ -0,0 +1,39 @@
+// payout math shared by the prediction contracts, so they pay out the same way
+
+// amount owed to a winning stake in a parimutuel pool:
+//     stake * pool / winning
+// where winning is the total staked on the winning outcome and pool is
+// everything staked across all outcomes. the division rounds down, so the
+// remainder stays in the pool and the payouts of every winner together can
+// never add up to more than pool. returns None on overflow, on a winning
+// total of zero, or if the stake is larger than the winning total
+pub fn share(stake: i128, winning: i128, pool: i128) -> Option<i128> {
+    if stake < 0 || winning <= 0 || stake > winning || pool < winning {
+        return None;
+    }
+    stake.checked_mul(pool)?.checked_div(winning)
+}
+
+// amount owed to the last winning stake to claim, which also collects the
+// rounding dust left behind by share(), so the pool is paid out exactly.
+// paid is the amount already sent to the other winners
+pub fn remainder(pool: i128, paid: i128) -> Option<i128> {
+    let rest = pool.checked_sub(paid)?;
+    if rest < 0 {
+        return None;
+    }
+    Some(rest)
+}
+
+// amount owed to a stake on an outcome that was given part of the pool, as in
+// a split or scalar resolution:
+//     stake * part / staked
+// where staked is the total staked on that outcome. rounds down like share(),
+// but part can be smaller than what was staked. returns None on overflow, on
+// a staked total of zero, or if the stake is larger than the staked total
+pub fn part(stake: i128, staked: i128, part: i128) -> Option<i128> {
+    if stake < 0 || staked <= 0 || stake > staked || part < 0 {
+        return None;
+    }
+    stake.checked_mul(part)?.checked_div(staked)
+}
@@ -0,0 +1,6 @@
+// ledger lifetimes used when extending contract storage
+
+// ledgers close roughly every 5 seconds
+pub const LEDGER_SECS: u64 = 5;
+// TTLs are only extended once they have dropped by this many ledgers, about a day
+pub const BUMP_MARGIN: u32 = (24 * 60 * 60 / LEDGER_SECS) as u32;
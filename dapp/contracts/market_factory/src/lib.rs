@@ -0,0 +1,206 @@
+#![no_std]
+use soroban_sdk::{Address, BytesN, contract, contracterror, contractimpl, contracttype, Env, IntoVal, String, Symbol, vec, Vec};
+use market_common::ttl::BUMP_MARGIN;
+
+// deploys prediction markets (VoteContract from prediction_contract_v2) from an
+// uploaded wasm hash, initialises them in the same call and keeps track of them
+// the factory is the contract admin of every market it deploys, so only the
+// factory admin can upgrade them, through upgrade_market(), or set the outcome
+// token wasm their share tokens are deployed from, through set_market_share_wasm()
+
+#[contracttype]
+// Admin can change the wasm hash new markets are deployed from
+// Market(index) entries are kept in persistent storage, MarketCount is the
+// number of markets deployed so far and the index of the next one
+// entries and the instance are extended to the longest TTL the network allows
+// whenever they are read or written, so the list outlives the markets in it
+pub enum Registry {
+    Admin,
+    WasmHash,
+    MarketCount,
+    Market(u32),
+}
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+// error codes returned by MarketFactory
+pub enum Error {
+    // init() was already called
+    AlreadyInit = 1,
+    // init() hasn't been called yet
+    NotInit = 2,
+    // the caller isn't the factory admin
+    AdminInvalid = 3,
+    // no market has been deployed at this index
+    NoMarket = 4,
+}
+
+#[contracttype]
+#[derive(Clone)]
+// struct for a market deployed by the factory
+pub struct MarketEntry {
+    pub market: Address,
+    pub creator: Address,
+    pub created_t: u64,
+}
+
+// salt for the market at index, so every market's address can be worked out
+// ahead of time from the factory address and its index
+fn salt(env: &Env, index: u32) -> BytesN<32> {
+    let mut salt = [0u8; 32];
+    salt[28..].copy_from_slice(&index.to_be_bytes());
+    BytesN::from_array(env, &salt)
+}
+
+// keeps the instance alive as long as allowed
+fn extend_instance(env: &Env) {
+    let ttl = env.storage().max_ttl();
+    env.storage().instance().extend_ttl(ttl - BUMP_MARGIN, ttl);
+}
+
+// keeps a Market entry and the instance alive as long as allowed
+fn extend_entry(env: &Env, key: &Registry) {
+    let ttl = env.storage().max_ttl();
+    env.storage().persistent().extend_ttl(key, ttl - BUMP_MARGIN, ttl);
+    extend_instance(env);
+}
+
+#[contract]
+pub struct MarketFactory;
+
+#[contractimpl]
+impl MarketFactory {
+
+    // called once after deployment, wasm_hash is the uploaded prediction contract wasm
+    pub fn init(env: Env, admin: Address, wasm_hash: BytesN<32>) -> Result<(), Error> {
+        if env.storage().instance().has(&Registry::Admin) {
+            return Err(Error::AlreadyInit);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&Registry::Admin, &admin);
+        env.storage().instance().set(&Registry::WasmHash, &wasm_hash);
+        env.storage().instance().set(&Registry::MarketCount, &0u32);
+        extend_instance(&env);
+        Ok(())
+    }
+
+    // function for the admin to change the wasm new markets are deployed from,
+    // markets already deployed are not affected
+    pub fn set_wasm_hash(env: Env, admin: Address, wasm_hash: BytesN<32>) -> Result<(), Error> {
+        Self::check_admin(&env, &admin)?;
+        env.storage().instance().set(&Registry::WasmHash, &wasm_hash);
+        extend_instance(&env);
+        Ok(())
+    }
+
+    // function for anyone to deploy and open a new market, the creator becomes
+    // the admin of its first market and the factory the contract admin. takes the same arguments as
+    // VoteContract::create_market(), the market is resolved by its admin and has
+    // market id 0 in the new contract. returns the new contract's Address
+    pub fn create_market(
+        env: Env,
+        creator: Address,
+        token: Address,
+        duration: u64,
+        outcomes: Vec<String>,
+    ) -> Result<Address, Error> {
+        creator.require_auth();
+        let wasm_hash: BytesN<32> = env.storage().instance().get(&Registry::WasmHash).ok_or(Error::NotInit)?;
+        let index = Self::view_market_count(env.clone());
+
+        let market = env.deployer().with_current_contract(salt(&env, index)).deploy(wasm_hash);
+        // VoteContract::init(admin), in the same call so nobody else can
+        let args = vec![&env, env.current_contract_address().into_val(&env)];
+        env.invoke_contract::<()>(&market, &Symbol::new(&env, "init"), args);
+        // VoteContract::create_market(admin, token, duration, outcomes, oracle), with no oracle
+        let args = vec![
+            &env,
+            creator.into_val(&env),
+            token.into_val(&env),
+            duration.into_val(&env),
+            outcomes.into_val(&env),
+            ().into_val(&env),
+        ];
+        env.invoke_contract::<u32>(&market, &Symbol::new(&env, "create_market"), args);
+
+        let entry = MarketEntry {
+            market: market.clone(),
+            creator,
+            created_t: env.ledger().timestamp(),
+        };
+        let key = Registry::Market(index);
+        env.storage().persistent().set(&key, &entry);
+        extend_entry(&env, &key);
+        env.storage().instance().set(&Registry::MarketCount, &(index + 1));
+        Ok(market)
+    }
+
+    // function for the admin to upgrade the market deployed at index to the
+    // uploaded wasm at new_wasm_hash, see VoteContract::upgrade()
+    pub fn upgrade_market(env: Env, admin: Address, index: u32, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        Self::check_admin(&env, &admin)?;
+        let entry = Self::view_market(env.clone(), index).ok_or(Error::NoMarket)?;
+        // VoteContract::upgrade(admin, new_wasm_hash), with the factory as admin
+        let args = vec![&env, env.current_contract_address().into_val(&env), new_wasm_hash.into_val(&env)];
+        env.invoke_contract::<()>(&entry.market, &Symbol::new(&env, "upgrade"), args);
+        Ok(())
+    }
+
+    // function for the admin to set the uploaded outcome token wasm the market
+    // deployed at index deploys share tokens from, see VoteContract::set_share_wasm()
+    // the market's own admins can then turn share tokens on with set_share_tokens()
+    pub fn set_market_share_wasm(env: Env, admin: Address, index: u32, wasm_hash: BytesN<32>) -> Result<(), Error> {
+        Self::check_admin(&env, &admin)?;
+        let entry = Self::view_market(env.clone(), index).ok_or(Error::NoMarket)?;
+        // VoteContract::set_share_wasm(admin, wasm_hash), with the factory as admin
+        let args = vec![&env, env.current_contract_address().into_val(&env), wasm_hash.into_val(&env)];
+        env.invoke_contract::<()>(&entry.market, &Symbol::new(&env, "set_share_wasm"), args);
+        Ok(())
+    }
+
+    // function to get the number of markets deployed so far
+    pub fn view_market_count(env: Env) -> u32 {
+        extend_instance(&env);
+        env.storage().instance().get(&Registry::MarketCount).unwrap_or(0)
+    }
+
+    // function to get the market deployed at index, if there is one
+    pub fn view_market(env: Env, index: u32) -> Option<MarketEntry> {
+        let key = Registry::Market(index);
+        let entry = env.storage().persistent().get(&key);
+        if entry.is_some() {
+            extend_entry(&env, &key);
+        }
+        entry
+    }
+
+    // function to list up to limit markets, starting from index start
+    pub fn view_markets(env: Env, start: u32, limit: u32) -> Vec<MarketEntry> {
+        let end = Self::view_market_count(env.clone()).min(start.saturating_add(limit));
+        let mut markets = Vec::new(&env);
+        for index in start..end {
+            if let Some(entry) = Self::view_market(env.clone(), index) {
+                markets.push_back(entry);
+            }
+        }
+        markets
+    }
+
+    // function to get the Address the market at index is (or will be) deployed to
+    pub fn view_market_address(env: Env, index: u32) -> Address {
+        env.deployer().with_current_contract(salt(&env, index)).deployed_address()
+    }
+
+    // internal function to check the caller is the factory admin
+    fn check_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+        admin.require_auth();
+        let stored: Address = env.storage().instance().get(&Registry::Admin).ok_or(Error::NotInit)?;
+        if stored != *admin {
+            return Err(Error::AdminInvalid);
+        }
+        Ok(())
+    }
+}
+
+mod test;
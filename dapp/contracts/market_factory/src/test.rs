@@ -0,0 +1,176 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{storage::Persistent as _, Address as _, AuthorizedFunction, Ledger};
+use soroban_sdk::{token, Env};
+
+// built from prediction_contract_v2, see the README on rebuilding it
+mod vote {
+    soroban_sdk::contractimport!(file = "fixtures/prediction_contract_v2.wasm");
+}
+
+// the share token wasm the v2 tests use
+mod outcome_token {
+    soroban_sdk::contractimport!(file = "../../../prediction_contract_v2/fixtures/outcome_token.wasm");
+}
+
+// a factory deploying the prediction contract wasm, and a token to stake in
+struct Setup<'a> {
+    env: Env,
+    admin: Address,
+    factory: MarketFactoryClient<'a>,
+    wasm_hash: BytesN<32>,
+    token: Address,
+}
+
+impl<'a> Setup<'a> {
+    fn new() -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+        // the deployed markets run as wasm, which costs more than the default test budget
+        env.budget().reset_unlimited();
+
+        let admin = Address::generate(&env);
+        let wasm_hash = env.deployer().upload_contract_wasm(vote::WASM);
+        let factory = MarketFactoryClient::new(&env, &env.register_contract(None, MarketFactory));
+        factory.init(&admin, &wasm_hash);
+        let token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+
+        Setup { env, admin, factory, wasm_hash, token }
+    }
+
+    fn create_market(&self, creator: &Address) -> Address {
+        let outcomes = vec![&self.env, String::from_str(&self.env, "yes"), String::from_str(&self.env, "no")];
+        self.factory.create_market(creator, &self.token, &100, &outcomes)
+    }
+}
+
+#[test]
+fn init() {
+    let s = Setup::new();
+
+    assert_eq!(s.factory.try_init(&s.admin, &s.wasm_hash), Err(Ok(Error::AlreadyInit)));
+    let other = Address::generate(&s.env);
+    assert_eq!(s.factory.try_set_wasm_hash(&other, &s.wasm_hash), Err(Ok(Error::AdminInvalid)));
+    s.factory.set_wasm_hash(&s.admin, &s.wasm_hash);
+
+    let fresh = MarketFactoryClient::new(&s.env, &s.env.register_contract(None, MarketFactory));
+    let outcomes = vec![&s.env, String::from_str(&s.env, "yes"), String::from_str(&s.env, "no")];
+    assert_eq!(fresh.try_create_market(&other, &s.token, &100, &outcomes), Err(Ok(Error::NotInit)));
+}
+
+#[test]
+fn create_market() {
+    let s = Setup::new();
+    let creator = Address::generate(&s.env);
+    let expected = s.factory.view_market_address(&0);
+
+    let market = s.create_market(&creator);
+    assert_eq!(market, expected);
+
+    // the creator signs once, for the factory call and the market it opens
+    let auths = s.env.auths();
+    assert_eq!(auths.len(), 1);
+    assert_eq!(auths[0].0, creator);
+    let AuthorizedFunction::Contract((ref contract, ref function, _)) = auths[0].1.function
+    else {
+        panic!("expected a contract call");
+    };
+    assert_eq!((contract, function), (&s.factory.address, &Symbol::new(&s.env, "create_market")));
+    let AuthorizedFunction::Contract((ref contract, ref function, _)) = auths[0].1.sub_invocations[0].function
+    else {
+        panic!("expected a contract call");
+    };
+    assert_eq!((contract, function), (&market, &Symbol::new(&s.env, "create_market")));
+
+    let entry = s.factory.view_market(&0).unwrap();
+    assert_eq!(entry.market, market);
+    assert_eq!(entry.creator, creator);
+    assert_eq!(s.factory.view_market_count(), 1);
+
+    // the creator runs market 0 of the new contract, the factory the contract itself
+    let client = vote::Client::new(&s.env, &market);
+    assert_eq!(client.view_market_count(), 1);
+    assert_eq!(client.view_admins(&0), vec![&s.env, creator.clone()]);
+    assert_eq!(client.view_token(&0), s.token);
+    assert_eq!(client.view_predictions(&0).end_t, 100);
+    assert_eq!(client.view_contract_admin(), Some(s.factory.address.clone()));
+
+    let user = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.token).mint(&user, &10);
+    client.record_votes(&0, &user, &0, &10);
+    assert_eq!(token::Client::new(&s.env, &s.token).balance(&market), 10);
+}
+
+#[test]
+fn view_markets() {
+    let s = Setup::new();
+    let creator = Address::generate(&s.env);
+    let markets = [s.create_market(&creator), s.create_market(&creator), s.create_market(&creator)];
+
+    for (index, market) in markets.iter().enumerate() {
+        assert_eq!(s.factory.view_market_address(&(index as u32)), *market);
+    }
+    assert_ne!(markets[0], markets[1]);
+    let page = s.factory.view_markets(&1, &5);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(0).unwrap().market, markets[1]);
+    assert_eq!(page.get(1).unwrap().market, markets[2]);
+    assert_eq!(s.factory.view_markets(&3, &5).len(), 0);
+    assert!(s.factory.view_market(&3).is_none());
+}
+
+#[test]
+fn upgrade_market() {
+    let s = Setup::new();
+    let creator = Address::generate(&s.env);
+    let market = s.create_market(&creator);
+    let client = vote::Client::new(&s.env, &market);
+
+    // the market's own admins can't upgrade a factory market
+    assert!(client.try_upgrade(&creator, &s.wasm_hash).is_err());
+    assert_eq!(s.factory.try_upgrade_market(&creator, &0, &s.wasm_hash), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.factory.try_upgrade_market(&s.admin, &1, &s.wasm_hash), Err(Ok(Error::NoMarket)));
+
+    s.factory.upgrade_market(&s.admin, &0, &s.wasm_hash);
+    assert_eq!(client.view_predictions(&0).end_t, 100);
+}
+
+#[test]
+fn set_market_share_wasm() {
+    let s = Setup::new();
+    let creator = Address::generate(&s.env);
+    let market = s.create_market(&creator);
+    let client = vote::Client::new(&s.env, &market);
+    let share_wasm = s.env.deployer().upload_contract_wasm(outcome_token::WASM);
+
+    // the market's admin can't set the wasm, only the factory admin through the factory
+    assert!(client.try_set_share_wasm(&creator, &share_wasm).is_err());
+    assert!(client.try_set_share_tokens(&0, &creator).is_err());
+    assert_eq!(s.factory.try_set_market_share_wasm(&creator, &0, &share_wasm), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.factory.try_set_market_share_wasm(&s.admin, &1, &share_wasm), Err(Ok(Error::NoMarket)));
+
+    s.factory.set_market_share_wasm(&s.admin, &0, &share_wasm);
+    let tokens = client.set_share_tokens(&0, &creator);
+    assert_eq!(client.view_share_tokens(&0), Some(tokens.clone()));
+
+    let user = Address::generate(&s.env);
+    token::StellarAssetClient::new(&s.env, &s.token).mint(&user, &10);
+    client.record_votes(&0, &user, &0, &10);
+    assert_eq!(token::Client::new(&s.env, &tokens.get(0).unwrap()).balance(&user), 10);
+}
+
+#[test]
+fn entries_are_extended_on_read() {
+    let s = Setup::new();
+    let creator = Address::generate(&s.env);
+    s.create_market(&creator);
+    let max = s.env.storage().max_ttl();
+    let ttl = || s.env.as_contract(&s.factory.address, || s.env.storage().persistent().get_ttl(&Registry::Market(0)));
+    assert_eq!(ttl(), max);
+
+    s.env.ledger().with_mut(|l| l.sequence_number += BUMP_MARGIN + 1);
+    assert!(ttl() < max - BUMP_MARGIN);
+    assert_eq!(s.factory.view_markets(&0, &1).len(), 1);
+    assert_eq!(ttl(), max);
+}
@@ -0,0 +1,27 @@
+#![no_std]
+use soroban_sdk::{contract, contractimpl, contracttype, Env};
+
+// stand-in for a real resolver contract, for testing markets that are
+// resolved through an oracle. anyone can set an outcome, don't deploy it
+// for real markets
+
+#[contracttype]
+pub enum Registry {
+    Outcome(u64),
+}
+
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    // function to set the winning outcome index for a market
+    pub fn set_outcome(env: Env, market_id: u64, outcome: u32) {
+        env.storage().instance().set(&Registry::Outcome(market_id), &outcome);
+    }
+
+    // function implementing the prediction contract's Oracle interface
+    pub fn outcome(env: Env, market_id: u64) -> Option<u32> {
+        env.storage().instance().get(&Registry::Outcome(market_id))
+    }
+}
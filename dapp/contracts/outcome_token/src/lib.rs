@@ -0,0 +1,260 @@
+#![no_std]
+use soroban_sdk::{Address, contract, contracterror, contractimpl, contracttype, Env, panic_with_error, String, symbol_short, token::{self, TokenInterface}};
+use market_common::ttl::BUMP_MARGIN;
+
+// SEP-41 token for the shares of one outcome of a prediction market, so positions
+// can be moved between wallets and traded. the prediction contract is the admin:
+// it mints shares to stakers and claws them back when they are claimed or refunded
+
+#[contracttype]
+// Admin is the prediction contract, Metadata the decimals, name and symbol
+// Balance entries are kept in persistent storage and extended to the longest TTL
+// the network allows whenever they are read or written, since a market can run
+// for longer than any fixed TTL. Allowance entries are kept in temporary storage
+// since they expire anyway
+pub enum Registry {
+    Admin,
+    Metadata,
+    Balance(Address),
+    Allowance(Address, Address),
+}
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+// error codes returned by OutcomeToken
+pub enum Error {
+    // init() was already called
+    AlreadyInit = 1,
+    // init() hasn't been called yet
+    NotInit = 2,
+    // amounts can't be negative
+    NegativeAmount = 3,
+    // the balance is lower than the amount
+    InsufficientBalance = 4,
+    // the allowance is lower than the amount
+    InsufficientAllowance = 5,
+    // an allowance has to expire at or after the current ledger
+    InvalidExpiration = 6,
+}
+
+#[contracttype]
+#[derive(Clone)]
+// struct for the token metadata, set once by init()
+pub struct Metadata {
+    pub decimals: u32,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[contracttype]
+#[derive(Clone)]
+// struct for an allowance given by approve(), void after expiration_ledger
+pub struct Allowance {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+#[contract]
+pub struct OutcomeToken;
+
+#[contractimpl]
+impl OutcomeToken {
+
+    // called once after deployment, admin should be the prediction contract the
+    // shares are for, decimals should match its staking token. the admin has to
+    // authorize it, so the prediction contract deploys and inits in the same call
+    pub fn init(env: Env, admin: Address, decimals: u32, name: String, symbol: String) -> Result<(), Error> {
+        if env.storage().instance().has(&Registry::Admin) {
+            return Err(Error::AlreadyInit);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&Registry::Admin, &admin);
+        env.storage().instance().set(&Registry::Metadata, &Metadata { decimals, name, symbol });
+        Self::extend_instance(&env);
+        Ok(())
+    }
+
+    // function for the admin to issue amount new shares to to
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let admin = Self::admin(&env);
+        admin.require_auth();
+        Self::check_amount(&env, amount);
+        Self::receive(&env, &to, amount);
+
+        env.events().publish((symbol_short!("mint"), admin, to), amount);
+    }
+
+    // function for the admin to take back and destroy amount shares held by from
+    pub fn clawback(env: Env, from: Address, amount: i128) {
+        let admin = Self::admin(&env);
+        admin.require_auth();
+        Self::check_amount(&env, amount);
+        Self::spend(&env, &from, amount);
+
+        env.events().publish((symbol_short!("clawback"), admin, from), amount);
+    }
+
+    // internal function to get the admin, fails if init() hasn't been called
+    fn admin(env: &Env) -> Address {
+        match env.storage().instance().get(&Registry::Admin) {
+            Some(admin) => admin,
+            None => panic_with_error!(env, Error::NotInit),
+        }
+    }
+
+    // internal function to read the metadata set by init()
+    fn metadata(env: &Env) -> Metadata {
+        match env.storage().instance().get(&Registry::Metadata) {
+            Some(metadata) => metadata,
+            None => panic_with_error!(env, Error::NotInit),
+        }
+    }
+
+    fn check_amount(env: &Env, amount: i128) {
+        if amount < 0 {
+            panic_with_error!(env, Error::NegativeAmount);
+        }
+    }
+
+    // internal function to add amount to id's balance
+    fn receive(env: &Env, id: &Address, amount: i128) {
+        let balance = Self::balance(env.clone(), id.clone());
+        Self::set_balance(env, id, balance + amount);
+    }
+
+    // internal function to take amount from id's balance, fails if it is too low
+    fn spend(env: &Env, id: &Address, amount: i128) {
+        let balance = Self::balance(env.clone(), id.clone());
+        if balance < amount {
+            panic_with_error!(env, Error::InsufficientBalance);
+        }
+        Self::set_balance(env, id, balance - amount);
+    }
+
+    fn set_balance(env: &Env, id: &Address, amount: i128) {
+        let key = Registry::Balance(id.clone());
+        env.storage().persistent().set(&key, &amount);
+        Self::extend_balance(env, &key);
+    }
+
+    // internal function to keep a balance and the instance alive as long as allowed
+    fn extend_balance(env: &Env, key: &Registry) {
+        let ttl = env.storage().max_ttl();
+        env.storage().persistent().extend_ttl(key, ttl - BUMP_MARGIN, ttl);
+        Self::extend_instance(env);
+    }
+
+    fn extend_instance(env: &Env) {
+        let ttl = env.storage().max_ttl();
+        env.storage().instance().extend_ttl(ttl - BUMP_MARGIN, ttl);
+    }
+
+    // internal function to read an allowance, expired allowances read as zero
+    fn read_allowance(env: &Env, from: &Address, spender: &Address) -> Allowance {
+        let key = Registry::Allowance(from.clone(), spender.clone());
+        match env.storage().temporary().get::<_, Allowance>(&key) {
+            Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+            _ => Allowance { amount: 0, expiration_ledger: 0 },
+        }
+    }
+
+    // internal function to take amount from spender's allowance on from's shares
+    fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+        let mut allowance = Self::read_allowance(env, from, spender);
+        if allowance.amount < amount {
+            panic_with_error!(env, Error::InsufficientAllowance);
+        }
+        if amount > 0 {
+            allowance.amount -= amount;
+            let key = Registry::Allowance(from.clone(), spender.clone());
+            env.storage().temporary().set(&key, &allowance);
+        }
+    }
+}
+
+#[contractimpl]
+impl token::TokenInterface for OutcomeToken {
+    fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        Self::read_allowance(&env, &from, &spender).amount
+    }
+
+    fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        Self::check_amount(&env, amount);
+        let ledger = env.ledger().sequence();
+        if amount > 0 && expiration_ledger < ledger {
+            panic_with_error!(&env, Error::InvalidExpiration);
+        }
+
+        let key = Registry::Allowance(from.clone(), spender.clone());
+        env.storage().temporary().set(&key, &Allowance { amount, expiration_ledger });
+        if amount > 0 {
+            let live = expiration_ledger - ledger;
+            env.storage().temporary().extend_ttl(&key, live, live);
+        }
+
+        env.events().publish((symbol_short!("approve"), from, spender), (amount, expiration_ledger));
+    }
+
+    fn balance(env: Env, id: Address) -> i128 {
+        let key = Registry::Balance(id);
+        match env.storage().persistent().get(&key) {
+            Some(balance) => {
+                Self::extend_balance(&env, &key);
+                balance
+            }
+            None => 0,
+        }
+    }
+
+    fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        Self::check_amount(&env, amount);
+        Self::spend(&env, &from, amount);
+        Self::receive(&env, &to, amount);
+
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        Self::check_amount(&env, amount);
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::spend(&env, &from, amount);
+        Self::receive(&env, &to, amount);
+
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+        Self::check_amount(&env, amount);
+        Self::spend(&env, &from, amount);
+
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        Self::check_amount(&env, amount);
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::spend(&env, &from, amount);
+
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    fn decimals(env: Env) -> u32 {
+        Self::metadata(&env).decimals
+    }
+
+    fn name(env: Env) -> String {
+        Self::metadata(&env).name
+    }
+
+    fn symbol(env: Env) -> String {
+        Self::metadata(&env).symbol
+    }
+}
+
+mod test;
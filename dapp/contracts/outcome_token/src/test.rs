@@ -0,0 +1,108 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{storage::Persistent as _, Address as _, Ledger};
+use soroban_sdk::Env;
+
+fn setup(env: &Env) -> (OutcomeTokenClient<'_>, Address) {
+    env.mock_all_auths();
+    let admin = Address::generate(env);
+    let client = OutcomeTokenClient::new(env, &env.register_contract(None, OutcomeToken));
+    client.init(&admin, &7, &String::from_str(env, "Yes shares"), &String::from_str(env, "YES"));
+    (client, admin)
+}
+
+#[test]
+fn init() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    assert_eq!(client.decimals(), 7);
+    assert_eq!(client.name(), String::from_str(&env, "Yes shares"));
+    assert_eq!(client.symbol(), String::from_str(&env, "YES"));
+    let res = client.try_init(&admin, &7, &String::from_str(&env, "a"), &String::from_str(&env, "A"));
+    assert_eq!(res, Err(Ok(Error::AlreadyInit)));
+}
+
+#[test]
+fn init_needs_admin_auth() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let client = OutcomeTokenClient::new(&env, &env.register_contract(None, OutcomeToken));
+
+    assert!(client.try_init(&admin, &7, &String::from_str(&env, "a"), &String::from_str(&env, "A")).is_err());
+    env.mock_all_auths();
+    client.init(&admin, &7, &String::from_str(&env, "a"), &String::from_str(&env, "A"));
+    assert_eq!(env.auths()[0].0, admin);
+}
+
+#[test]
+fn balances_are_extended_on_read() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    client.mint(&a, &100);
+    let ttl = || env.as_contract(&client.address, || env.storage().persistent().get_ttl(&Registry::Balance(a.clone())));
+    assert_eq!(ttl(), env.storage().max_ttl());
+
+    env.ledger().with_mut(|l| l.sequence_number += BUMP_MARGIN + 1);
+    assert!(ttl() < env.storage().max_ttl() - BUMP_MARGIN);
+    assert_eq!(client.balance(&a), 100);
+    assert_eq!(ttl(), env.storage().max_ttl());
+}
+
+#[test]
+fn mint_transfer_clawback() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+
+    client.mint(&a, &100);
+    client.transfer(&a, &b, &40);
+    assert_eq!(client.balance(&a), 60);
+    assert_eq!(client.balance(&b), 40);
+
+    client.clawback(&b, &40);
+    assert_eq!(client.balance(&b), 0);
+    assert!(client.try_transfer(&a, &b, &61).is_err());
+    assert!(client.try_transfer(&a, &b, &-1).is_err());
+    assert_eq!(client.balance(&a), 60);
+}
+
+#[test]
+fn approve_and_transfer_from() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let spender = Address::generate(&env);
+    client.mint(&a, &100);
+
+    client.approve(&a, &spender, &50, &100);
+    assert_eq!(client.allowance(&a, &spender), 50);
+    client.transfer_from(&spender, &a, &b, &30);
+    assert_eq!(client.allowance(&a, &spender), 20);
+    assert_eq!(client.balance(&b), 30);
+    assert!(client.try_transfer_from(&spender, &a, &b, &21).is_err());
+
+    client.burn_from(&spender, &a, &20);
+    assert_eq!(client.balance(&a), 50);
+    assert_eq!(client.allowance(&a, &spender), 0);
+}
+
+#[test]
+fn allowance_expires() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    let spender = Address::generate(&env);
+    client.mint(&a, &100);
+    env.ledger().with_mut(|l| l.sequence_number = 10);
+
+    assert!(client.try_approve(&a, &spender, &50, &9).is_err());
+    client.approve(&a, &spender, &50, &20);
+    env.ledger().with_mut(|l| l.sequence_number = 21);
+    assert_eq!(client.allowance(&a, &spender), 0);
+    assert!(client.try_transfer_from(&spender, &a, &spender, &1).is_err());
+}
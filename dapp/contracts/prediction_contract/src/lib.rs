@@ -1,13 +1,18 @@
 #![no_std]
 use soroban_sdk::{Address, contract, contracttype, contractimpl, Env, log, Symbol, symbol_short, token};
+use market_common::payout;
 
 #[contracttype]
 // struct for mapping user Address to their votes for this Contract
 // Admin will be populated with the admin Address on contract init()
+// Claimed is set for a user once claim() has paid them, Claims holds the running
+// totals of every claim, kept apart from Record so stored records still decode
 pub enum Registry {
     Record(Address),
     Admin(Address),
     Prediction,
+    Claimed(Address),
+    Claims,
 }
 
 #[contracttype]
@@ -39,6 +44,16 @@ pub struct Record {
     pub time: u64,
 }
 
+#[contracttype]
+#[derive(Clone)]
+// struct for the claims paid so far, so the last winner can be paid the rounding dust
+pub struct ClaimTotals {
+    // winning votes that have been claimed
+    pub votes: u64,
+    // amount paid out by claim()
+    pub paid: i128,
+}
+
 const OPT1: Symbol = symbol_short!("OPT1");
 const OPT2: Symbol = symbol_short!("OPT2");
 const NONE: Symbol = symbol_short!("none");
@@ -55,10 +70,7 @@ impl VoteContract {
     ) {
         // check if init() has already been called for this contract
         let mut pred = Self::view_predictions(env.clone());
-        if pred.has_init == 1 {
-            panic!("Already init");
-        }
-        else if pred.start_t != 0 {
+        if pred.has_init == 1 || pred.start_t != 0 {
             panic!("Already init");
         }
         else {
@@ -99,17 +111,17 @@ impl VoteContract {
             records.votes = votes;
             records.time = time;
             if records.selected == OPT1 {
-                pred.opt_1 = pred.opt_1 + votes;
+                pred.opt_1 += votes;
             }
             else if records.selected == OPT2 {
-                pred.opt_2 = pred.opt_2 + votes;
+                pred.opt_2 += votes;
             }
-            pred.total = pred.total + votes;
+            pred.total += votes;
             env.storage().instance().set(&Registry::Record(user), &records);
             env.storage().instance().set(&Registry::Prediction, &pred);
             env.storage().instance().extend_ttl(100, 100);
 
-            return symbol_short!("Recorded");
+            symbol_short!("Recorded")
         }
     }
 
@@ -173,61 +185,67 @@ impl VoteContract {
             pred.winner = winner;
             env.storage().instance().set(&Registry::Prediction, &pred);
             env.storage().instance().extend_ttl(100, 100);
-            return pred;
+            pred
         }
     }
 
     // function for stakers to claim the earnings based on vote once market is closed
-    // returns the amount given to the user
+    // returns the amount given to the user, each user can only claim once
     // contract: the prediction contract Address, used for sending XLM to stakers
-    pub fn claim(env: Env, user: Address, xlm: Address, contract: Address) {
-        // verify caller with stored Record
-        let key = Registry::Record(user.clone());
+    pub fn claim(env: Env, user: Address, xlm: Address, contract: Address) -> i128 {
         user.require_auth();
 
         let poll = Self::view_predictions(env.clone());
-        let user_record = env.storage().instance().get(&key).unwrap_or(0);
-        
-        // if user has a record and market is open, not valid
-        if user_record == 0 {
+        let records = Self::view_voter(env.clone(), user.clone());
+        if records.votes == 0 {
             panic!("No user record");
         }
-        else if poll.has_init == 0 {
-            panic!("Market not closed");
-        }
         else if poll.winner == NONE {
             panic!("Market not closed");
         }
-        else {
-            // TokenClient with the XLM Contract address
-            let client = token::TokenClient::new(&env, &xlm);
-            let records = Self::view_voter(env.clone(), user.clone());
-            
-            // allocate XLM payments from current pool, based on user's vote 
-            // proportion of total, since each vote means a certain amount bet
-            // formula = voter's full vote amount + proportion of votes from the losing pool
-            let opt1_votes: i128 = poll.opt_1 as i128;
-            let opt2_votes: i128 = poll.opt_2 as i128;
-            let user_votes: i128 = records.votes as i128;
-
-            // note that precision is limited due to rounding
-            if poll.winner == records.selected {
-                if records.selected == OPT1 {
-                    let mut payout: i128 = (user_votes * opt2_votes) * opt1_votes;
-                    payout += user_votes;
-                    let final_pay: i128 = payout as i128;
-                    client.transfer(&contract, &user, &final_pay);
-                }
-                else {
-                    let mut payout: i128 = (user_votes * opt1_votes) * opt2_votes;
-                    payout += user_votes;
-                    let final_pay: i128 = payout as i128;
-                    client.transfer(&contract, &user, &final_pay);
-                }
-            }
-            else {
-                panic!("No valid claimings");
-            }
+        else if poll.winner != records.selected {
+            panic!("No valid claimings");
+        }
+        else if env.storage().instance().has(&Registry::Claimed(user.clone())) {
+            panic!("Already claimed");
         }
+
+        // allocate XLM payments from current pool, based on user's vote
+        // proportion of the winning votes, since each vote means a certain amount bet
+        // formula = user votes * total votes / winning votes, rounded down, and the
+        // last winner to claim takes what is left so the pool is paid out exactly
+        let winning_votes = if records.selected == OPT1 { poll.opt_1 } else { poll.opt_2 };
+        let mut claims = Self::view_claims(env.clone());
+        let payout = if claims.votes + records.votes == winning_votes {
+            payout::remainder(poll.total as i128, claims.paid)
+        }
+        else {
+            payout::share(records.votes as i128, winning_votes as i128, poll.total as i128)
+        };
+        let payout = match payout {
+            Some(payout) => payout,
+            None => panic!("Payout overflow"),
+        };
+
+        // mark the user as paid before sending anything
+        claims.votes += records.votes;
+        claims.paid += payout;
+        env.storage().instance().set(&Registry::Claimed(user.clone()), &true);
+        env.storage().instance().set(&Registry::Claims, &claims);
+        env.storage().instance().extend_ttl(100, 100);
+
+        // TokenClient with the XLM Contract address
+        let client = token::TokenClient::new(&env, &xlm);
+        client.transfer(&contract, &user, &payout);
+        payout
     }
-}
\ No newline at end of file
+
+    // function to view the claims paid so far
+    pub fn view_claims(env: Env) -> ClaimTotals {
+        env.storage().instance().get(&Registry::Claims).unwrap_or(ClaimTotals {
+            votes: 0,
+            paid: 0,
+        })
+    }
+}
+mod test;
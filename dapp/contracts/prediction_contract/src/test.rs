@@ -0,0 +1,199 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::Env;
+
+fn setup(env: &Env) -> (VoteContractClient<'_>, Address) {
+    env.mock_all_auths();
+    let admin = Address::generate(env);
+    let contract_id = env.register_contract(None, VoteContract);
+    let client = VoteContractClient::new(env, &contract_id);
+    client.init(&admin, &100);
+    (client, admin)
+}
+
+#[test]
+fn init() {
+    let env = Env::default();
+    env.ledger().with_mut(|l| l.timestamp = 1000);
+    let (client, admin) = setup(&env);
+
+    let pred = client.view_predictions();
+    assert_eq!(pred.has_init, 1);
+    assert_eq!(pred.start_t, 1000);
+    assert_eq!(pred.end_t, 1100);
+    assert_eq!(pred.total, 0);
+    assert_eq!(pred.winner, NONE);
+    assert_eq!(client.view_admin(&admin).has_init, 1);
+}
+
+#[test]
+#[should_panic(expected = "Already init")]
+fn init_twice() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    client.init(&admin, &100);
+}
+
+#[test]
+fn record_votes() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+
+    assert_eq!(client.record_votes(&a, &OPT1, &30), symbol_short!("Recorded"));
+    client.record_votes(&b, &OPT2, &20);
+
+    let pred = client.view_predictions();
+    assert_eq!(pred.opt_1, 30);
+    assert_eq!(pred.opt_2, 20);
+    assert_eq!(pred.total, 50);
+    let records = client.view_voter(&a);
+    assert_eq!(records.selected, OPT1);
+    assert_eq!(records.votes, 30);
+}
+
+#[test]
+#[should_panic(expected = "Cannot vote")]
+fn record_votes_twice() {
+    let env = Env::default();
+    env.ledger().with_mut(|l| l.timestamp = 1000);
+    let (client, _) = setup(&env);
+    let user = Address::generate(&env);
+
+    client.record_votes(&user, &OPT1, &30);
+    client.record_votes(&user, &OPT2, &30);
+}
+
+#[test]
+fn close() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let pred = client.close(&admin, &OPT2);
+    assert_eq!(pred.winner, OPT2);
+    assert_eq!(client.view_predictions().winner, OPT2);
+}
+
+#[test]
+#[should_panic(expected = "Admin invalid")]
+fn close_by_non_admin() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+
+    client.close(&Address::generate(&env), &OPT1);
+}
+
+#[test]
+#[should_panic(expected = "Invalid winner")]
+fn close_invalid_winner() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    client.close(&admin, &symbol_short!("OPT3"));
+}
+
+#[test]
+#[should_panic(expected = "Predictions already closed")]
+fn close_twice() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    client.close(&admin, &OPT1);
+    client.close(&admin, &OPT1);
+}
+
+// registers an XLM stand-in and funds the contract with the whole pool, as the
+// dapp does with the payment sent alongside record_votes
+fn fund(env: &Env, client: &VoteContractClient, pool: i128) -> token::TokenClient<'static> {
+    let xlm = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    token::StellarAssetClient::new(env, &xlm).mint(&client.address, &pool);
+    token::TokenClient::new(env, &xlm)
+}
+
+#[test]
+fn claim_pays_winners() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    client.record_votes(&a, &OPT1, &1);
+    client.record_votes(&b, &OPT1, &2);
+    client.record_votes(&c, &OPT2, &7);
+    let xlm = fund(&env, &client, 10);
+    client.close(&admin, &OPT1);
+
+    // 1 * 10 / 3 rounds down, the last winner takes the dust
+    assert_eq!(client.claim(&a, &xlm.address, &client.address), 3);
+    assert_eq!(client.claim(&b, &xlm.address, &client.address), 7);
+    assert_eq!(xlm.balance(&a), 3);
+    assert_eq!(xlm.balance(&b), 7);
+    assert_eq!(xlm.balance(&client.address), 0);
+
+    let claims = client.view_claims();
+    assert_eq!(claims.votes, 3);
+    assert_eq!(claims.paid, 10);
+}
+
+#[test]
+#[should_panic(expected = "Already claimed")]
+fn claim_twice() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    client.record_votes(&a, &OPT1, &5);
+    client.record_votes(&b, &OPT2, &5);
+    let xlm = fund(&env, &client, 10);
+    client.close(&admin, &OPT1);
+
+    client.claim(&a, &xlm.address, &client.address);
+    client.claim(&a, &xlm.address, &client.address);
+}
+
+#[test]
+#[should_panic(expected = "No valid claimings")]
+fn claim_losing_side() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    client.record_votes(&a, &OPT1, &5);
+    client.record_votes(&b, &OPT2, &5);
+    let xlm = fund(&env, &client, 10);
+    client.close(&admin, &OPT1);
+
+    client.claim(&b, &xlm.address, &client.address);
+}
+
+#[test]
+#[should_panic(expected = "Market not closed")]
+fn claim_before_close() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+    let a = Address::generate(&env);
+    client.record_votes(&a, &OPT1, &5);
+    let xlm = fund(&env, &client, 5);
+
+    client.claim(&a, &xlm.address, &client.address);
+}
+
+#[test]
+fn claim_needs_user_auth() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let a = Address::generate(&env);
+    client.record_votes(&a, &OPT1, &5);
+    let xlm = fund(&env, &client, 5);
+    client.close(&admin, &OPT1);
+
+    // claiming needs the staker to sign
+    env.set_auths(&[]);
+    assert!(client.try_claim(&a, &xlm.address, &client.address).is_err());
+    env.mock_all_auths();
+    assert_eq!(client.claim(&a, &xlm.address, &client.address), 5);
+}
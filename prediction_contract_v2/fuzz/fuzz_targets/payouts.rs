@@ -0,0 +1,41 @@
+#![no_main]
+
+// cargo +nightly fuzz run payouts
+// drives a market with arbitrary steps, see prediction_contract_v2::harness for
+// the invariants checked
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use prediction_contract_v2::harness::{self, Step};
+
+#[derive(Arbitrary, Debug)]
+enum Input {
+    Stake { user: u8, outcome: u8, votes: u32 },
+    Pass { secs: u16 },
+    Lock,
+    Close { winner: u8 },
+    Cancel,
+    Claim { user: u8 },
+    Refund { user: u8 },
+}
+
+impl From<&Input> for Step {
+    fn from(input: &Input) -> Self {
+        // outcomes past the end are still tried, just not every u8 of them
+        let outcome = |i: u8| i as u32 % (harness::OUTCOMES + 1);
+        match *input {
+            Input::Stake { user, outcome: o, votes } => Step::Stake { user, outcome: outcome(o), votes },
+            Input::Pass { secs } => Step::Pass { secs },
+            Input::Lock => Step::Lock,
+            Input::Close { winner } => Step::Close { winner: outcome(winner) },
+            Input::Cancel => Step::Cancel,
+            Input::Claim { user } => Step::Claim { user },
+            Input::Refund { user } => Step::Refund { user },
+        }
+    }
+}
+
+fuzz_target!(|input: Vec<Input>| {
+    let steps: Vec<Step> = input.iter().take(64).map(Step::from).collect();
+    harness::run(&steps);
+});
@@ -0,0 +1,185 @@
+use soroban_sdk::{Address, contractimpl, contracttype, Env, token};
+
+use crate::{events, Error, MarketStatus, PredictionRecord, Registry, VoteContract, VoteContractClient};
+
+// longest dispute window accepted, 30 days
+const MAX_WINDOW: u64 = 30 * 24 * 60 * 60;
+// how long the arbiter has to rule on a challenge, 30 days, after which anyone
+// can call expire_dispute() so the market doesn't wait on them forever
+pub const ARBITRATION_TIMEOUT: u64 = 30 * 24 * 60 * 60;
+
+#[contracttype]
+#[derive(Clone)]
+// struct for the dispute rules of a market, set by an admin with set_dispute()
+// arbiter: the Address that rules on challenged outcomes
+// window: seconds after a proposed outcome during which it can be challenged, at
+// least 1 and at most MAX_WINDOW
+// bond: amount of the stake token a challenger has to put up, more than 0
+pub struct DisputeConfig {
+    pub arbiter: Address,
+    pub window: u64,
+    pub bond: i128,
+}
+
+#[contracttype]
+#[derive(Clone)]
+// struct for an open challenge against the proposed outcome
+pub struct Challenge {
+    pub challenger: Address,
+    // the outcome the challenger says is the real winner
+    pub outcome: u32,
+    pub bond: i128,
+    // time the challenge was made, when the arbiter's ARBITRATION_TIMEOUT starts
+    pub time: u64,
+}
+
+#[contractimpl]
+impl VoteContract {
+    // function for admins to give the market a dispute window, only while it is open
+    // once set, close() and resolve() only propose a winner, which becomes final
+    // after the window through finalize(), or after the arbiter rules on a challenge
+    pub fn set_dispute(env: Env, market_id: u32, admin: Address, config: DisputeConfig) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if Self::view_scalar(env.clone(), market_id).is_some() {
+            return Err(Error::ScalarMarket);
+        }
+        if config.bond <= 0 {
+            return Err(Error::InvalidBond);
+        }
+        if config.window == 0 || config.window > MAX_WINDOW {
+            return Err(Error::InvalidWindow);
+        }
+        env.storage().persistent().set(&Registry::Dispute(market_id), &config);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::dispute(&env, market_id, &admin, &config);
+        Ok(pred)
+    }
+
+    // function to view the market's dispute rules, if any
+    pub fn view_dispute(env: Env, market_id: u32) -> Option<DisputeConfig> {
+        env.storage().persistent().get(&Registry::Dispute(market_id))
+    }
+
+    // function to view the open challenge, if any
+    pub fn view_challenge(env: Env, market_id: u32) -> Option<Challenge> {
+        env.storage().persistent().get(&Registry::Challenge(market_id))
+    }
+
+    // function for a staker to challenge the proposed outcome during the dispute
+    // window, the bond is transferred to the contract and the market waits on the
+    // arbiter. only the first challenge is taken
+    pub fn challenge(env: Env, market_id: u32, user: Address, outcome: u32) -> Result<PredictionRecord, Error> {
+        user.require_auth();
+        let mut pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_dispute(env.clone(), market_id).ok_or(Error::NoDispute)?;
+        if pred.status != MarketStatus::Proposed {
+            return Err(Error::MarketNotProposed);
+        }
+        if env.ledger().timestamp() > pred.proposed_t.saturating_add(config.window) {
+            return Err(Error::DisputeWindowClosed);
+        }
+        if Self::view_voter(env.clone(), market_id, user.clone()).total == 0 {
+            return Err(Error::NoUserRecord);
+        }
+        if outcome >= pred.totals.len() {
+            return Err(Error::InvalidOutcome);
+        }
+        if pred.winner == Some(outcome) {
+            return Err(Error::SameOutcome);
+        }
+        Self::move_status(&mut pred, MarketStatus::Disputed)?;
+
+        let challenge = Challenge {
+            challenger: user.clone(),
+            outcome,
+            bond: config.bond,
+            time: env.ledger().timestamp(),
+        };
+        env.storage().persistent().set(&Registry::Challenge(market_id), &challenge);
+        Self::save_market(&env, market_id, &pred);
+
+        if config.bond > 0 {
+            let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+            client.transfer(&user, &env.current_contract_address(), &config.bond);
+        }
+
+        events::challenge(&env, market_id, &user, outcome, config.bond);
+        Ok(pred)
+    }
+
+    // function anyone can call to make an unchallenged proposed outcome final
+    // once the dispute window has passed, claims open after this
+    pub fn finalize(env: Env, market_id: u32) -> Result<PredictionRecord, Error> {
+        let mut pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_dispute(env.clone(), market_id).ok_or(Error::NoDispute)?;
+        if pred.status != MarketStatus::Proposed {
+            return Err(Error::MarketNotProposed);
+        }
+        if env.ledger().timestamp() <= pred.proposed_t.saturating_add(config.window) {
+            return Err(Error::DisputeWindowOpen);
+        }
+        Self::move_status(&mut pred, MarketStatus::Resolved)?;
+        Self::save_market(&env, market_id, &pred);
+
+        let winner = pred.winner.ok_or(Error::MarketNotProposed)?;
+        events::resolve(&env, market_id, &env.current_contract_address(), winner, &pred.totals);
+        Ok(pred)
+    }
+
+    // function for the arbiter to rule on a challenge by naming the final winner
+    // upholding the proposed outcome slashes the bond into the winners' pool,
+    // any other outcome returns the bond to the challenger
+    pub fn arbitrate(env: Env, market_id: u32, arbiter: Address, winner: u32) -> Result<PredictionRecord, Error> {
+        arbiter.require_auth();
+        let mut pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_dispute(env.clone(), market_id).ok_or(Error::NoDispute)?;
+        if config.arbiter != arbiter {
+            return Err(Error::NotArbiter);
+        }
+        if pred.status != MarketStatus::Disputed {
+            return Err(Error::MarketNotDisputed);
+        }
+        let challenge = Self::view_challenge(env.clone(), market_id).ok_or(Error::MarketNotDisputed)?;
+        if winner >= pred.totals.len() {
+            return Err(Error::InvalidOutcome);
+        }
+        env.storage().persistent().remove(&Registry::Challenge(market_id));
+
+        let upheld = pred.winner == Some(winner);
+        if upheld {
+            pred.slashed += challenge.bond;
+        }
+        else if challenge.bond > 0 {
+            let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+            client.transfer(&env.current_contract_address(), &challenge.challenger, &challenge.bond);
+        }
+        events::arbitrate(&env, market_id, &arbiter, &challenge.challenger, winner, upheld);
+        Self::finish(&env, market_id, &arbiter, pred, winner)
+    }
+
+    // function anyone can call once the arbiter has let ARBITRATION_TIMEOUT pass
+    // without ruling, cancels the market so every staker can refund() and returns
+    // the bond to the challenger. the cancel event has the contract as its caller
+    pub fn expire_dispute(env: Env, market_id: u32) -> Result<PredictionRecord, Error> {
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Disputed {
+            return Err(Error::MarketNotDisputed);
+        }
+        let challenge = Self::view_challenge(env.clone(), market_id).ok_or(Error::MarketNotDisputed)?;
+        if env.ledger().timestamp() <= challenge.time.saturating_add(ARBITRATION_TIMEOUT) {
+            return Err(Error::ArbitrationPending);
+        }
+        env.storage().persistent().remove(&Registry::Challenge(market_id));
+
+        if challenge.bond > 0 {
+            let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+            client.transfer(&env.current_contract_address(), &challenge.challenger, &challenge.bond);
+        }
+        Self::cancel_market(&env, market_id, &env.current_contract_address(), pred)
+    }
+}
@@ -0,0 +1,181 @@
+use soroban_sdk::{Address, BytesN, Env, String, Symbol, symbol_short, Vec};
+
+use crate::DisputeConfig;
+
+// events published by VoteContract, the first topic is always the event name
+// so the dapp can filter on it with getEvents. for market events the second is
+// the market id and the third is the Address that triggered it, for contract
+// events the second is the contract admin
+
+pub const INIT: Symbol = symbol_short!("init");
+pub const SHARE_WASM: Symbol = symbol_short!("share_wsm");
+pub const CREATE: Symbol = symbol_short!("create");
+pub const STAKE: Symbol = symbol_short!("stake");
+pub const LOCK: Symbol = symbol_short!("lock");
+pub const RESOLVE: Symbol = symbol_short!("resolve");
+pub const CLAIM: Symbol = symbol_short!("claim");
+pub const CANCEL: Symbol = symbol_short!("cancel");
+pub const REFUND: Symbol = symbol_short!("refund");
+pub const PROPOSE: Symbol = symbol_short!("propose");
+pub const CHALLENGE: Symbol = symbol_short!("challenge");
+pub const ARBITRATE: Symbol = symbol_short!("arbitrate");
+pub const ADMIN_PROPOSE: Symbol = symbol_short!("adm_prop");
+pub const ADMIN_TRANSFER: Symbol = symbol_short!("adm_xfer");
+pub const ADMIN_ADD: Symbol = symbol_short!("adm_add");
+pub const ADMIN_REMOVE: Symbol = symbol_short!("adm_rm");
+pub const UPGRADE: Symbol = symbol_short!("upgrade");
+pub const MIGRATE: Symbol = symbol_short!("migrate");
+pub const LMSR: Symbol = symbol_short!("lmsr");
+pub const BUY: Symbol = symbol_short!("buy");
+pub const RECLAIM: Symbol = symbol_short!("reclaim");
+pub const SHARES: Symbol = symbol_short!("shares");
+pub const EXIT: Symbol = symbol_short!("exit");
+pub const SPLIT: Symbol = symbol_short!("split");
+pub const LOCK_PERIOD: Symbol = symbol_short!("lock_prd");
+pub const DISPUTE: Symbol = symbol_short!("dispute");
+pub const EXIT_FEE: Symbol = symbol_short!("exit_fee");
+
+// contract claimed by its admin, data: ()
+pub fn init(env: &Env, admin: &Address) {
+    env.events().publish((INIT, admin.clone()), ());
+}
+
+// outcome token wasm for share tokens set, data: the wasm hash
+pub fn share_wasm(env: &Env, admin: &Address, wasm_hash: &BytesN<32>) {
+    env.events().publish((SHARE_WASM, admin.clone()), wasm_hash.clone());
+}
+
+// market opened, data: (token, start_t, end_t, outcomes)
+pub fn create(env: &Env, market_id: u32, admin: &Address, token: &Address, start_t: u64, end_t: u64, outcomes: &Vec<String>) {
+    env.events().publish(
+        (CREATE, market_id, admin.clone()),
+        (token.clone(), start_t, end_t, outcomes.clone()),
+    );
+}
+
+// stake recorded, data: (outcome index, votes)
+pub fn stake(env: &Env, market_id: u32, user: &Address, selected: u32, votes: u64) {
+    env.events().publish((STAKE, market_id, user.clone()), (selected, votes));
+}
+
+// stake taken back before end_t, data: (outcome index, votes, fee kept)
+pub fn exit(env: &Env, market_id: u32, user: &Address, outcome: u32, votes: u64, fee: i128) {
+    env.events().publish((EXIT, market_id, user.clone()), (outcome, votes, fee));
+}
+
+// exit fee set, data: fee in basis points
+pub fn exit_fee(env: &Env, market_id: u32, admin: &Address, fee_bps: u32) {
+    env.events().publish((EXIT_FEE, market_id, admin.clone()), fee_bps);
+}
+
+// market switched to LMSR pricing, data: (liquidity b, subsidy paid in)
+pub fn lmsr(env: &Env, market_id: u32, admin: &Address, b: u64, subsidy: i128) {
+    env.events().publish((LMSR, market_id, admin.clone()), (b, subsidy));
+}
+
+// shares bought in an LMSR market, data: (outcome index, shares, cost)
+pub fn buy(env: &Env, market_id: u32, user: &Address, outcome: u32, shares: u64, cost: i128) {
+    env.events().publish((BUY, market_id, user.clone()), (outcome, shares, cost));
+}
+
+// LMSR subsidy and surplus paid back to the provider, data: amount
+pub fn reclaim(env: &Env, market_id: u32, provider: &Address, amount: i128) {
+    env.events().publish((RECLAIM, market_id, provider.clone()), amount);
+}
+
+// outcome-share tokens set for the market, data: the token of each outcome
+pub fn shares(env: &Env, market_id: u32, admin: &Address, tokens: &Vec<Address>) {
+    env.events().publish((SHARES, market_id, admin.clone()), tokens.clone());
+}
+
+// lock period after end_t set, data: lock_t
+pub fn lock_period(env: &Env, market_id: u32, admin: &Address, lock_t: u64) {
+    env.events().publish((LOCK_PERIOD, market_id, admin.clone()), lock_t);
+}
+
+// dispute window set, data: (arbiter, window, bond)
+pub fn dispute(env: &Env, market_id: u32, admin: &Address, config: &DisputeConfig) {
+    env.events().publish((DISPUTE, market_id, admin.clone()), (config.arbiter.clone(), config.window, config.bond));
+}
+
+// market stopped taking stakes, data: end_t
+pub fn lock(env: &Env, market_id: u32, admin: &Address, end_t: u64) {
+    env.events().publish((LOCK, market_id, admin.clone()), end_t);
+}
+
+// market closed with a winner, topic is the admin or oracle that resolved it, the
+// arbiter that ruled on a challenge, or the contract's own Address when finalize()
+// made a proposed winner final, data: (winning index, totals per outcome)
+pub fn resolve(env: &Env, market_id: u32, admin: &Address, winner: u32, totals: &Vec<u64>) {
+    env.events().publish((RESOLVE, market_id, admin.clone()), (winner, totals.clone()));
+}
+
+// market resolved by splitting the pool, data: the part of the pool each outcome got
+pub fn split(env: &Env, market_id: u32, by: &Address, split: &Vec<i128>) {
+    env.events().publish((SPLIT, market_id, by.clone()), split.clone());
+}
+
+// winner proposed, open to challenges until the dispute window passes,
+// data: proposed winning index
+pub fn propose(env: &Env, market_id: u32, by: &Address, winner: u32) {
+    env.events().publish((PROPOSE, market_id, by.clone()), winner);
+}
+
+// proposed winner challenged, data: (outcome the challenger backs, bond)
+pub fn challenge(env: &Env, market_id: u32, user: &Address, outcome: u32, bond: i128) {
+    env.events().publish((CHALLENGE, market_id, user.clone()), (outcome, bond));
+}
+
+// arbiter ruled on a challenge, data: (challenger, final winning index,
+// whether the proposed winner was upheld and the bond slashed)
+pub fn arbitrate(env: &Env, market_id: u32, arbiter: &Address, challenger: &Address, winner: u32, upheld: bool) {
+    env.events().publish((ARBITRATE, market_id, arbiter.clone()), (challenger.clone(), winner, upheld));
+}
+
+// payout sent to a winning staker, data: amount paid
+pub fn claim(env: &Env, market_id: u32, user: &Address, amount: i128) {
+    env.events().publish((CLAIM, market_id, user.clone()), amount);
+}
+
+// market voided, stakes can be refunded, topic is the admin that cancelled it, the
+// admin, oracle or arbiter that named an outcome nobody staked on, or the
+// contract's own Address when expire_dispute() cancelled it, data: total votes to refund
+pub fn cancel(env: &Env, market_id: u32, admin: &Address, total: u64) {
+    env.events().publish((CANCEL, market_id, admin.clone()), total);
+}
+
+// stake returned from a cancelled market, data: amount refunded
+pub fn refund(env: &Env, market_id: u32, user: &Address, amount: i128) {
+    env.events().publish((REFUND, market_id, user.clone()), amount);
+}
+
+// admin transfer proposed, data: the proposed new admin
+pub fn admin_propose(env: &Env, market_id: u32, admin: &Address, new_admin: &Address) {
+    env.events().publish((ADMIN_PROPOSE, market_id, admin.clone()), new_admin.clone());
+}
+
+// admin transfer accepted, topic is the old admin, data: the new admin
+pub fn admin_transfer(env: &Env, market_id: u32, old_admin: &Address, new_admin: &Address) {
+    env.events().publish((ADMIN_TRANSFER, market_id, old_admin.clone()), new_admin.clone());
+}
+
+// admin rights granted, data: the new admin
+pub fn admin_add(env: &Env, market_id: u32, admin: &Address, new_admin: &Address) {
+    env.events().publish((ADMIN_ADD, market_id, admin.clone()), new_admin.clone());
+}
+
+// admin rights revoked, data: the removed admin
+pub fn admin_remove(env: &Env, market_id: u32, admin: &Address, old_admin: &Address) {
+    env.events().publish((ADMIN_REMOVE, market_id, admin.clone()), old_admin.clone());
+}
+
+// contract code replaced, topic is the contract admin that upgraded it,
+// data: the new wasm hash
+pub fn upgrade(env: &Env, admin: &Address, wasm_hash: &BytesN<32>) {
+    env.events().publish((UPGRADE, admin.clone()), wasm_hash.clone());
+}
+
+// storage brought up to the current schema, data: (old version, new version)
+pub fn migrate(env: &Env, from: u32, to: u32) {
+    env.events().publish((MIGRATE,), (from, to));
+}
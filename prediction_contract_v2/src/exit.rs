@@ -0,0 +1,96 @@
+use soroban_sdk::{Address, contractimpl, Env, token};
+
+use crate::{events, Error, MarketStatus, PredictionRecord, Registry, VoteContract, VoteContractClient};
+
+// exits: while a market is open a staker can take back part or all of a stake
+// an exit fee set by the admins, in basis points of the votes exited, stays in the
+// market and is added to the winners' pool like a slashed bond, so moving in and
+// out of a position to push the odds around costs something
+
+// fees are in basis points, 10_000 is the whole stake
+const MAX_FEE_BPS: u32 = 10_000;
+
+#[contractimpl]
+impl VoteContract {
+    // function for admins to set the exit fee in basis points, only while the market
+    // is open. markets start with no fee
+    pub fn set_exit_fee(env: Env, market_id: u32, admin: Address, fee_bps: u32) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if fee_bps > MAX_FEE_BPS {
+            return Err(Error::InvalidExitFee);
+        }
+        env.storage().persistent().set(&Registry::ExitFee(market_id), &fee_bps);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::exit_fee(&env, market_id, &admin, fee_bps);
+        Ok(pred)
+    }
+
+    // function to view the market's exit fee in basis points
+    pub fn view_exit_fee(env: Env, market_id: u32) -> u32 {
+        env.storage().persistent().get(&Registry::ExitFee(market_id)).unwrap_or(0)
+    }
+
+    // function for a staker to take votes back off an outcome before end_t, the
+    // stake less the exit fee is transferred back in the same call
+    // in a market with share tokens the user's tokens for the outcome are burned
+    // returns the amount paid back
+    pub fn exit_position(env: Env, market_id: u32, user: Address, outcome: u32, votes: u64) -> Result<i128, Error> {
+        user.require_auth();
+        if votes == 0 {
+            return Err(Error::InvalidVotes);
+        }
+        let mut pred = Self::load_market(&env, market_id)?;
+        if Self::view_lmsr(env.clone(), market_id).is_some() {
+            return Err(Error::LmsrMarket);
+        }
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if env.ledger().timestamp() > pred.end_t {
+            return Err(Error::MarketEnded);
+        }
+        let staked = pred.totals.get(outcome).ok_or(Error::InvalidOutcome)?;
+
+        // with share tokens the position is whatever the user holds, which can differ
+        // from what they staked themselves
+        let mut records = Self::view_voter(env.clone(), market_id, user.clone());
+        let position = records.votes.get(outcome).unwrap_or(0);
+        let share_token = Self::view_share_tokens(env.clone(), market_id).and_then(|tokens| tokens.get(outcome));
+        let held = match &share_token {
+            Some(token) => u64::try_from(token::TokenClient::new(&env, token).balance(&user)).unwrap_or(0),
+            None => position,
+        };
+        if votes > held {
+            return Err(Error::NotEnoughVotes);
+        }
+
+        // the fee rounds up, so splitting an exit into small ones doesn't avoid it
+        let fee_bps = Self::view_exit_fee(env.clone(), market_id) as u128;
+        let fee = (votes as u128 * fee_bps).div_ceil(MAX_FEE_BPS as u128) as u64;
+        let amount = (votes - fee) as i128;
+
+        records.votes.set(outcome, position.saturating_sub(votes));
+        records.total = records.total.saturating_sub(votes);
+        pred.totals.set(outcome, staked - votes);
+        pred.total -= votes;
+        pred.slashed += fee as i128;
+        Self::save_voter(&env, market_id, &user, &records, &pred);
+        Self::save_market(&env, market_id, &pred);
+
+        if let Some(token) = share_token {
+            token::StellarAssetClient::new(&env, &token).clawback(&user, &(votes as i128));
+        }
+        if amount > 0 {
+            let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+            client.transfer(&env.current_contract_address(), &user, &amount);
+        }
+
+        events::exit(&env, market_id, &user, outcome, votes, fee as i128);
+        Ok(amount)
+    }
+}
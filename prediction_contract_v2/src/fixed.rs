@@ -0,0 +1,59 @@
+// fixed-point math on i128 with 18 decimals, for the LMSR cost function in lmsr.rs
+// the contract is no_std so there is no f64, everything here is integer only
+
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+// ln(2) * SCALE
+pub const LN_2: i128 = 693_147_180_559_945_309;
+
+// past this e^x is below 1 / SCALE and rounds to 0
+const EXP_MIN: i128 = -42 * SCALE;
+
+// e^x for x <= 0, all values scaled by SCALE
+// x = -(k * ln(2) + r) with 0 <= r < ln(2), so e^x = e^-r / 2^k, and e^-r comes
+// from its Taylor series, which converges fast for r that small
+pub fn exp_neg(x: i128) -> i128 {
+    if x <= EXP_MIN {
+        return 0;
+    }
+    let y = -x.min(0);
+    let k = y / LN_2;
+    let r = y - k * LN_2;
+
+    let mut sum = SCALE;
+    let mut term = SCALE;
+    let mut i = 1;
+    while term != 0 {
+        term = -term * r / (i * SCALE);
+        sum += term;
+        i += 1;
+    }
+    sum >> k
+}
+
+// ln(x) for x > 0, all values scaled by SCALE
+// x = m * 2^k with 1 <= m < 2, so ln(x) = k * ln(2) + ln(m), and ln(m) comes from
+// 2 * atanh(z) with z = (m - 1) / (m + 1) < 1/3, whose series converges fast
+pub fn ln(x: i128) -> i128 {
+    let mut m = x;
+    let mut k = 0;
+    while m >= 2 * SCALE {
+        m >>= 1;
+        k += 1;
+    }
+    while m < SCALE {
+        m <<= 1;
+        k -= 1;
+    }
+
+    let z = (m - SCALE) * SCALE / (m + SCALE);
+    let z2 = z * z / SCALE;
+    let mut sum = 0;
+    let mut term = z;
+    let mut i = 1;
+    while term != 0 {
+        sum += term / i;
+        term = term * z2 / SCALE;
+        i += 2;
+    }
+    k * LN_2 + 2 * sum
+}
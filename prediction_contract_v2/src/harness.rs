@@ -0,0 +1,173 @@
+use soroban_sdk::testutils::{Address as _, EnvTestConfig, Ledger};
+use soroban_sdk::{token, vec, Address, Env, String};
+
+use crate::{MarketStatus, VoteContract, VoteContractClient};
+
+// drives a single market through a sequence of steps and checks the payout
+// invariants after every one, shared by the proptest in test.rs and the fuzz target
+// in fuzz/. steps that the contract rejects are fine, they just have to leave
+// balances untouched
+
+// stakers taking part, steps pick one by index modulo USERS
+pub const USERS: usize = 4;
+// outcomes of the market, steps can also name one past the end
+pub const OUTCOMES: u32 = 3;
+// seconds the market is open for
+pub const DURATION: u64 = 100;
+// starting balance of every staker, more than any sequence of stakes can spend
+const BALANCE: i128 = 1 << 50;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Step {
+    Stake { user: u8, outcome: u32, votes: u32 },
+    Pass { secs: u16 },
+    Lock,
+    Close { winner: u32 },
+    Cancel,
+    Claim { user: u8 },
+    Refund { user: u8 },
+}
+
+struct Market<'a> {
+    contract: VoteContractClient<'a>,
+    token: token::Client<'a>,
+    admin: Address,
+    users: [Address; USERS],
+    // votes each user has staked on each outcome
+    staked: [[u64; OUTCOMES as usize]; USERS],
+    // whether each user has been paid by claim() or refund()
+    paid: [bool; USERS],
+    // tokens sent in by stakes and out by claims and refunds
+    total_in: i128,
+    total_out: i128,
+}
+
+// runs steps against a fresh market, panics if an invariant doesn't hold
+// once the steps are done every remaining claim or refund is made, after which
+// the contract has to hold nothing
+pub fn run(steps: &[Step]) {
+    let env = Env::new_with_config(EnvTestConfig { capture_snapshot_at_drop: false });
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token_admin = token::StellarAssetClient::new(&env, &sac.address());
+    let users = [(); USERS].map(|_| {
+        let user = Address::generate(&env);
+        token_admin.mint(&user, &BALANCE);
+        user
+    });
+
+    let contract = VoteContractClient::new(&env, &env.register_contract(None, VoteContract));
+    contract.init(&admin);
+    let outcomes = vec![
+        &env,
+        String::from_str(&env, "a"),
+        String::from_str(&env, "b"),
+        String::from_str(&env, "c"),
+    ];
+    contract.create_market(&admin, &sac.address(), &DURATION, &outcomes, &None);
+
+    let mut m = Market {
+        contract,
+        token: token::Client::new(&env, &sac.address()),
+        admin,
+        users,
+        staked: [[0; OUTCOMES as usize]; USERS],
+        paid: [false; USERS],
+        total_in: 0,
+        total_out: 0,
+    };
+    for step in steps {
+        m.step(&env, *step);
+        m.check();
+    }
+
+    match m.contract.view_predictions(&0).status {
+        MarketStatus::Resolved => (0..USERS as u8).for_each(|user| m.step(&env, Step::Claim { user })),
+        MarketStatus::Cancelled => (0..USERS as u8).for_each(|user| m.step(&env, Step::Refund { user })),
+        _ => return,
+    }
+    m.check();
+    assert_eq!(m.token.balance(&m.contract.address), 0, "stakes left in a settled market");
+}
+
+impl Market<'_> {
+    fn step(&mut self, env: &Env, step: Step) {
+        match step {
+            Step::Stake { user, outcome, votes } => {
+                let i = user as usize % USERS;
+                let before = self.token.balance(&self.users[i]);
+                let ok = self.contract.try_record_votes(&0, &self.users[i], &outcome, &(votes as u64)).is_ok();
+                let after = self.token.balance(&self.users[i]);
+                if ok {
+                    assert_eq!(before - after, votes as i128, "stake moved the wrong amount");
+                    self.staked[i][outcome as usize] += votes as u64;
+                    self.total_in += votes as i128;
+                }
+                else {
+                    assert_eq!(before, after, "rejected stake moved tokens");
+                }
+            }
+            Step::Pass { secs } => env.ledger().with_mut(|l| l.timestamp += secs as u64),
+            Step::Lock => {
+                let _ = self.contract.try_lock(&0, &self.admin);
+            }
+            Step::Close { winner } => {
+                let _ = self.contract.try_close(&0, &self.admin, &winner);
+            }
+            Step::Cancel => {
+                let _ = self.contract.try_cancel(&0, &self.admin);
+            }
+            Step::Claim { user } => {
+                let i = user as usize % USERS;
+                let before = self.token.balance(&self.users[i]);
+                let res = self.contract.try_claim(&0, &self.users[i]);
+                let after = self.token.balance(&self.users[i]);
+                let pred = self.contract.view_predictions(&0);
+                let stake = pred.winner.map_or(0, |w| self.staked[i][w as usize]);
+                match res {
+                    Ok(Ok(amount)) => {
+                        assert!(stake > 0, "loser was paid");
+                        assert!(!self.paid[i], "claimed twice");
+                        assert!(amount >= stake as i128, "winner got less than their stake");
+                        assert_eq!(after - before, amount, "claim paid a different amount");
+                        self.paid[i] = true;
+                        self.total_out += amount;
+                    }
+                    _ => {
+                        assert!(stake == 0 || self.paid[i] || pred.status != MarketStatus::Resolved, "winner couldn't claim");
+                        assert_eq!(before, after, "failed claim moved tokens");
+                    }
+                }
+            }
+            Step::Refund { user } => {
+                let i = user as usize % USERS;
+                let before = self.token.balance(&self.users[i]);
+                let res = self.contract.try_refund(&0, &self.users[i]);
+                let after = self.token.balance(&self.users[i]);
+                let stake: u64 = self.staked[i].iter().sum();
+                match res {
+                    Ok(Ok(amount)) => {
+                        assert!(!self.paid[i], "refunded twice");
+                        assert_eq!(amount, stake as i128, "refund isn't the stake");
+                        assert_eq!(after - before, amount, "refund paid a different amount");
+                        self.paid[i] = true;
+                        self.total_out += amount;
+                    }
+                    _ => assert_eq!(before, after, "failed refund moved tokens"),
+                }
+            }
+        }
+    }
+
+    // invariants that hold after every step
+    fn check(&self) {
+        let balance = self.token.balance(&self.contract.address);
+        assert!(balance >= 0, "contract balance is negative");
+        assert!(self.total_out <= self.total_in, "paid out more than was staked");
+        assert_eq!(balance, self.total_in - self.total_out, "contract balance doesn't match stakes and payouts");
+        let pred = self.contract.view_predictions(&0);
+        assert_eq!(pred.total as i128, self.total_in, "market total doesn't match stakes");
+        assert!(pred.paid <= pred.total as i128, "market paid more than its pool");
+    }
+}
@@ -1,29 +1,242 @@
 #![no_std]
-use soroban_sdk::{Address, contract, contracttype, contractimpl, Env, log, Symbol, symbol_short, token};
+use soroban_sdk::{Address, contract, contracterror, contracttype, contractimpl, Env, log, String, Symbol, symbol_short, token, Vec};
+use market_common::{payout, ttl::{BUMP_MARGIN, LEDGER_SECS}};
+
+mod dispute;
+mod events;
+mod exit;
+mod fixed;
+mod lmsr;
+mod oracle;
+mod scalar;
+mod shares;
+mod upgrade;
+
+#[cfg(any(test, feature = "testutils"))]
+pub mod harness;
+
+pub use dispute::{Challenge, DisputeConfig, ARBITRATION_TIMEOUT};
+pub use lmsr::LmsrConfig;
+pub use oracle::{Oracle, OracleClient, OracleConfig};
+pub use scalar::{ScalarConfig, LONG, SHORT};
+pub use upgrade::SCHEMA_VERSION;
 
 #[contracttype]
-// struct for mapping user Address to their votes for this Contract
-// Admin will be populated with the admin Address on contract init()
+// storage keys, everything but MarketCount is keyed by the market id
+// Record maps a user Address to their votes in a market
+// Admin will be populated with the admin Address on create_market()
+// Admins lists every current admin, PendingAdmin holds a proposed admin transfer
+// Token holds the Address of the token contract stakes are made in
+// Oracle holds the OracleConfig for markets resolved by an oracle contract
+// Dispute holds the DisputeConfig and Challenge the open challenge, if any
+// Lmsr holds the LmsrConfig for markets priced by LMSR, see lmsr.rs
+// ShareTokens holds the outcome-share tokens of a market and ShareWasm the outcome
+// token wasm they are deployed from, see shares.rs
+// ExitFee holds the fee charged by exit_position() in basis points, see exit.rs
+// Scalar holds the ScalarConfig for markets on a numeric value, see scalar.rs
+// Split holds the part of the pool each outcome was given when a market is resolved
+// by splitting it rather than naming one winner
+// MarketCount is the number of markets created so far and the id of the next one
+// Version is the schema version storage is in, see upgrade.rs
+// ContractAdmin administers the contract itself rather than a market, set by init()
+// market data is kept in persistent storage so the instance doesn't grow with
+// every market and voter, only MarketCount, Version, ContractAdmin and ShareWasm
+// live in instance storage
 pub enum Registry {
-    Record(Address),
-    Admin(Address),
-    Prediction,
+    Record(u32, Address),
+    Admin(u32, Address),
+    Admins(u32),
+    PendingAdmin(u32),
+    Prediction(u32),
+    Token(u32),
+    Oracle(u32),
+    Dispute(u32),
+    Challenge(u32),
+    Lmsr(u32),
+    ShareTokens(u32),
+    ShareWasm,
+    ExitFee(u32),
+    Scalar(u32),
+    Split(u32),
+    MarketCount,
+    Version,
+    ContractAdmin,
+}
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+// error codes returned by VoteContract, clients match on the number
+pub enum Error {
+    // there is no market with this id
+    NoMarket = 2,
+    // create_market() was given fewer than MIN_OUTCOMES outcomes
+    NotEnoughOutcomes = 3,
+    // a stake of zero votes
+    InvalidVotes = 4,
+    // the outcome index is out of range
+    InvalidOutcome = 6,
+    // the caller isn't a registered admin
+    AdminInvalid = 7,
+    // the market isn't accepting stakes or changes any more
+    MarketNotOpen = 8,
+    // the ledger timestamp is past end_t
+    MarketEnded = 9,
+    // end_t and the lock period haven't passed yet
+    MarketRunning = 10,
+    // the status change isn't allowed by MarketStatus::can_move_to
+    InvalidStatusChange = 11,
+    // the user has no stake in this market
+    NoUserRecord = 12,
+    // the market doesn't have a winner yet
+    MarketNotResolved = 13,
+    // the user's stake isn't on the winning outcome
+    NothingToClaim = 14,
+    // the user's record has already been paid out
+    AlreadyClaimed = 15,
+    // the payout doesn't fit in an i128 or would overdraw the pool
+    PayoutOverflow = 16,
+    // refunds are only given out for cancelled markets
+    MarketNotCancelled = 17,
+    // there is no admin transfer waiting for this address
+    NoPendingAdmin = 18,
+    // removing this admin would leave the market with none
+    LastAdmin = 19,
+    // the address is already an admin
+    AlreadyAdmin = 20,
+    // the market is resolved by its oracle, not by an admin
+    OracleMarket = 21,
+    // the market has no oracle to resolve it
+    NoOracle = 22,
+    // the oracle doesn't have an outcome for the market yet
+    OracleNoOutcome = 23,
+    // the market has no dispute window
+    NoDispute = 24,
+    // the market doesn't have a proposed outcome waiting on the dispute window
+    MarketNotProposed = 25,
+    // the market doesn't have a challenge waiting on the arbiter
+    MarketNotDisputed = 26,
+    // the dispute window hasn't passed yet
+    DisputeWindowOpen = 27,
+    // the dispute window has already passed
+    DisputeWindowClosed = 28,
+    // the caller isn't the market's arbiter
+    NotArbiter = 29,
+    // a challenge has to name a different outcome than the proposed one
+    SameOutcome = 30,
+    // a dispute bond has to be more than 0
+    InvalidBond = 31,
+    // the market is waiting on the arbiter and can't be cancelled by an admin
+    MarketDisputed = 32,
+    // storage is on an older schema version, migrate() has to be called first
+    MigrationPending = 33,
+    // the market is priced by LMSR, shares are bought with buy_shares()
+    LmsrMarket = 34,
+    // the market isn't priced by LMSR
+    NotLmsr = 35,
+    // the LMSR liquidity parameter is zero or too large
+    InvalidLiquidity = 36,
+    // the market already has stakes or LMSR pricing
+    MarketNotEmpty = 37,
+    // the shares cost more than the buyer's max_cost
+    CostTooHigh = 38,
+    // the market already has outcome-share tokens
+    SharesSet = 39,
+    // the contract admin hasn't set the wasm share tokens are deployed from
+    NoShareWasm = 40,
+    // the exit fee is more than the whole stake
+    InvalidExitFee = 41,
+    // the position is smaller than the votes being exited
+    NotEnoughVotes = 42,
+    // a scalar market's lower bound has to be below its upper bound
+    InvalidBounds = 43,
+    // the market is scalar, it is resolved with close_scalar()
+    ScalarMarket = 44,
+    // the market isn't scalar
+    NotScalar = 45,
+    // split weights have to be one per outcome and add up to WEIGHT_TOTAL
+    InvalidWeights = 46,
+    // the market has a dispute window, only a single winner can be proposed
+    DisputeMarket = 47,
+    // init() was already called
+    AlreadyInit = 48,
+    // init() hasn't been called yet, no markets can be created
+    NotInit = 49,
+    // a dispute window has to be at least a second and at most 30 days
+    InvalidWindow = 50,
+    // the arbiter still has time to rule on the challenge
+    ArbitrationPending = 51,
+    // end_t, the lock period and the claim window after them don't fit in a timestamp
+    InvalidPeriod = 52,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+// lifecycle of a market, it only ever moves forward:
+// Open -> Locked -> Resolved, and Open or Locked -> Cancelled
+// with a dispute window a winner is first Proposed, then Resolved once the window
+// passes, or Disputed until the arbiter resolves it, or it is cancelled once the
+// arbiter has run out of time
+pub enum MarketStatus {
+    // accepting stakes
+    Open = 0,
+    // no new stakes, waiting on the winner
+    Locked = 1,
+    // winner set, winning stakers can claim
+    Resolved = 2,
+    // market voided, no winner will be set
+    Cancelled = 3,
+    // winner proposed, can be challenged until the dispute window passes
+    Proposed = 4,
+    // proposed winner challenged, waiting on the arbiter
+    Disputed = 5,
+}
+
+impl MarketStatus {
+    // whether a market in this status is allowed to move to next
+    pub fn can_move_to(self, next: MarketStatus) -> bool {
+        matches!(
+            (self, next),
+            (MarketStatus::Open, MarketStatus::Locked)
+                | (MarketStatus::Open | MarketStatus::Locked, MarketStatus::Proposed)
+                | (MarketStatus::Open | MarketStatus::Locked, MarketStatus::Resolved)
+                | (MarketStatus::Open | MarketStatus::Locked, MarketStatus::Cancelled)
+                | (MarketStatus::Proposed, MarketStatus::Resolved)
+                | (MarketStatus::Proposed, MarketStatus::Disputed)
+                | (MarketStatus::Disputed, MarketStatus::Resolved)
+                | (MarketStatus::Disputed, MarketStatus::Cancelled)
+        )
+    }
 }
 
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PredictionRecord {
     pub has_init: u64,
+    pub status: MarketStatus,
     pub start_t: u64,
     pub end_t: u64,
-    pub opt_1: u64,
-    pub opt_2: u64,
+    // seconds after end_t before the market can be closed, set by the admin
+    pub lock_t: u64,
+    // outcome labels, indexed by position
+    pub outcomes: Vec<String>,
+    // total votes staked on each outcome, same indexing as outcomes
+    pub totals: Vec<u64>,
     pub total: u64,
-    pub winner: Symbol,
+    pub winner: Option<u32>,
+    // winning votes that have been claimed so far
+    pub claimed_votes: u64,
+    // total amount paid out by claim() so far
+    pub paid: i128,
+    // time the winner was proposed, when the dispute window starts
+    pub proposed_t: u64,
+    // challenge bonds slashed and exit fees kept, added to the winners' pool
+    pub slashed: i128,
 }
 
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 // struct for admin address
 pub struct AdminRecord {
     pub has_init: u64,
@@ -31,17 +244,61 @@ pub struct AdminRecord {
 }
 
 #[contracttype]
-#[derive(Clone)]
-// struct for vote option, amount voted
+#[derive(Clone, Debug, Eq, PartialEq)]
+// struct for an admin transfer waiting to be accepted by the new admin
+pub struct AdminTransfer {
+    pub from: Address,
+    pub to: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// struct for a user's position, votes staked on each outcome and combined
 pub struct Record {
-    pub selected: Symbol,
-    pub votes: u64,
+    // votes staked on each outcome, same indexing as PredictionRecord.totals
+    pub votes: Vec<u64>,
+    // votes staked across all outcomes
+    pub total: u64,
+    // time of the latest stake
     pub time: u64,
+    // set once claim() has paid this record out
+    pub claimed: bool,
 }
 
-const OPT1: Symbol = symbol_short!("OPT1");
-const OPT2: Symbol = symbol_short!("OPT2");
-const NONE: Symbol = symbol_short!("none");
+// a market needs at least two outcomes to be worth betting on
+const MIN_OUTCOMES: u32 = 2;
+// split weights are in basis points, they add up to the whole pool
+pub const WEIGHT_TOTAL: u32 = 10_000;
+
+// how long stakers have to claim or refund once the market can be closed, 30 days
+const CLAIM_WINDOW: u64 = 30 * 24 * 60 * 60;
+
+// number of ledgers the market's data has to live from now on, until the claim
+// window after end_t and the lock period, or a full claim window if that has passed
+fn ttl_ledgers(env: &Env, pred: &PredictionRecord) -> u32 {
+    let now = env.ledger().timestamp();
+    let until = claim_deadline(pred.end_t, pred.lock_t).unwrap_or(u64::MAX).max(now.saturating_add(CLAIM_WINDOW));
+    let ledgers = (until - now) / LEDGER_SECS;
+    ledgers.min(env.storage().max_ttl() as u64) as u32
+}
+
+// end_t plus the lock period plus the claim window, so the deadlines worked out
+// from a market can never overflow once it has been saved
+fn claim_deadline(end_t: u64, lock_t: u64) -> Result<u64, Error> {
+    end_t
+        .checked_add(lock_t)
+        .and_then(|t| t.checked_add(CLAIM_WINDOW))
+        .ok_or(Error::InvalidPeriod)
+}
+
+// a Vec of len zeroes, one per outcome
+fn zeros(env: &Env, len: u32) -> Vec<u64> {
+    let mut v = Vec::new(env);
+    for _ in 0..len {
+        v.push_back(0);
+    }
+    v
+}
 
 #[contract]
 pub struct VoteContract;
@@ -49,187 +306,808 @@ pub struct VoteContract;
 #[contractimpl]
 impl VoteContract {
 
-    // called after contract deployment by the admin to enable voting for this contract
-    // should be called only once, adds the admin init to storage
-    pub fn init(env: Env, admin: Address, duration: u64,
-    ) {
-        // check if init() has already been called for this contract
-        let mut pred = Self::view_predictions(env.clone());
-        if pred.has_init == 1 {
-            panic!("Already init");
+    // called once right after deployment, sets the admin of the contract itself
+    // who can upgrade() it. they don't administer any market unless they open one
+    // markets can only be created once this has been called
+    pub fn init(env: Env, admin: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&Registry::ContractAdmin) {
+            return Err(Error::AlreadyInit);
         }
-        else if pred.start_t != 0 {
-            panic!("Already init");
+        admin.require_auth();
+        env.storage().instance().set(&Registry::ContractAdmin, &admin);
+
+        events::init(&env, &admin);
+        Ok(())
+    }
+
+    // function to get the admin of the contract itself, if init() has been called
+    pub fn view_contract_admin(env: Env) -> Option<Address> {
+        env.storage().instance().get(&Registry::ContractAdmin)
+    }
+
+    // called by anyone to open a new market in this contract, the caller becomes
+    // its admin. returns the market id every other function takes
+    // token: the Address of the SEP-41 token contract used for staking and payouts
+    // outcomes: the labels of each option, votes refer to these by index
+    // oracle: if given, the market is resolved by that oracle through resolve()
+    // instead of by an admin through close()
+    pub fn create_market(
+        env: Env,
+        admin: Address,
+        token: Address,
+        duration: u64,
+        outcomes: Vec<String>,
+        oracle: Option<OracleConfig>,
+    ) -> Result<u32, Error> {
+        if outcomes.len() < MIN_OUTCOMES {
+            return Err(Error::NotEnoughOutcomes);
         }
-        else {
-            // add admin address to registry
-            admin.require_auth();
-            let admin_rec = AdminRecord {
-                has_init: 1,
-            };
-            env.storage().instance().set(&Registry::Admin(admin), &admin_rec);
-            // add contract start and end time
-            pred.start_t = env.ledger().timestamp();
-            pred.end_t = env.ledger().timestamp() + duration;
-            // finally assert that the contract has been init, set the rest of the values
-            pred.has_init = 1;
-            pred.winner = NONE;
-            pred.opt_1 = 0;
-            pred.opt_2 = 0;
-            pred.total = 0;
-            env.storage().instance().set(&Registry::Prediction, &pred);
-            env.storage().instance().extend_ttl(100, 100);
-        }
-    }
-
-    // function to stake votes for a prediction, in the dapp this must be called along
-    // with the payment transaction for the correct amount
-    // Note: each user can only stake votes once in this version of the contract
-    pub fn record_votes(env: Env, user: Address, selected: Symbol, votes: u64) -> Symbol {
-        let mut records = Self::view_voter(env.clone(), user.clone());
+        admin.require_auth();
+        Self::check_version(&env)?;
+        if Self::view_contract_admin(env.clone()).is_none() {
+            return Err(Error::NotInit);
+        }
+        let end_t = env.ledger().timestamp().checked_add(duration).ok_or(Error::InvalidPeriod)?;
+        claim_deadline(end_t, 0)?;
+        let market_id = Self::view_market_count(env.clone());
+        let mut pred = Self::view_predictions(env.clone(), market_id);
+
+        // add admin address to registry
+        Self::set_admin(&env, market_id, &admin, true);
+        env.storage().persistent().set(&Registry::Token(market_id), &token);
+        if let Some(oracle) = oracle {
+            env.storage().persistent().set(&Registry::Oracle(market_id), &oracle);
+        }
+        // add contract start and end time
+        pred.start_t = env.ledger().timestamp();
+        pred.end_t = end_t;
+        pred.lock_t = 0;
+        // finally assert that the contract has been init, set the rest of the values
+        pred.has_init = 1;
+        pred.status = MarketStatus::Open;
+        pred.winner = None;
+        pred.totals = zeros(&env, outcomes.len());
+        pred.outcomes = outcomes;
+        pred.total = 0;
+        pred.claimed_votes = 0;
+        pred.paid = 0;
+        pred.proposed_t = 0;
+        pred.slashed = 0;
+        env.storage().instance().set(&Registry::MarketCount, &(market_id + 1));
+        env.storage().instance().set(&Registry::Version, &SCHEMA_VERSION);
+        Self::save_market(&env, market_id, &pred);
+
+        events::create(&env, market_id, &admin, &token, pred.start_t, pred.end_t, &pred.outcomes);
+        Ok(market_id)
+    }
+
+    // function to get the number of markets created so far, market ids run from
+    // 0 up to this
+    pub fn view_market_count(env: Env) -> u32 {
+        env.storage().instance().get(&Registry::MarketCount).unwrap_or(0)
+    }
+
+    // function to stake votes for a prediction, each vote is one unit of the token
+    // and is transferred from the user to the contract in the same call
+    // repeated calls add to the user's position, on the same or a different outcome
+    pub fn record_votes(env: Env, market_id: u32, user: Address, selected: u32, votes: u64) -> Result<Symbol, Error> {
+        let mut records = Self::view_voter(env.clone(), market_id, user.clone());
         user.require_auth();
         let time = env.ledger().timestamp();
-        // check for invalid values (no votes, time already stamped in record)
-        if votes == 0 || records.time != 0 {
-            panic!("Cannot vote");
+        // check for invalid values (no votes)
+        if votes == 0 {
+            return Err(Error::InvalidVotes);
         }
-        else {
-            let mut pred = Self::view_predictions(env.clone());
-            records.selected = selected;
-            records.votes = votes;
-            records.time = time;
-            if records.selected == OPT1 {
-                pred.opt_1 = pred.opt_1 + votes;
-            }
-            else if records.selected == OPT2 {
-                pred.opt_2 = pred.opt_2 + votes;
-            }
-            pred.total = pred.total + votes;
-            env.storage().instance().set(&Registry::Record(user), &records);
-            env.storage().instance().set(&Registry::Prediction, &pred);
-            env.storage().instance().extend_ttl(100, 100);
 
-            return symbol_short!("Recorded");
+        let mut pred = Self::load_market(&env, market_id)?;
+        if Self::view_lmsr(env.clone(), market_id).is_some() {
+            return Err(Error::LmsrMarket);
+        }
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if time > pred.end_t {
+            return Err(Error::MarketEnded);
         }
+        let staked = pred.totals.get(selected).ok_or(Error::InvalidOutcome)?;
+        let position = records.votes.get(selected).unwrap_or(0);
+        records.votes.set(selected, position + votes);
+        records.total += votes;
+        records.time = time;
+        pred.totals.set(selected, staked + votes);
+        pred.total += votes;
+
+        // pull the stake into the contract, fails the whole call if the user can't pay
+        let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+        client.transfer(&user, &env.current_contract_address(), &(votes as i128));
+        Self::mint_shares(&env, market_id, &user, selected, votes);
+
+        Self::save_voter(&env, market_id, &user, &records, &pred);
+        Self::save_market(&env, market_id, &pred);
+
+        events::stake(&env, market_id, &user, selected, votes);
+
+        Ok(symbol_short!("Recorded"))
     }
 
     // function to get Poll market values, also provides initial values for the struct
-    pub fn view_predictions(env: Env) -> PredictionRecord {
+    pub fn view_predictions(env: Env, market_id: u32) -> PredictionRecord {
         // let key = Registry::Poll(POLL);
-        env.storage().instance().get(&Registry::Prediction).unwrap_or(PredictionRecord {
+        env.storage().persistent().get(&Registry::Prediction(market_id)).unwrap_or(PredictionRecord {
             has_init: 0,
+            status: MarketStatus::Open,
             start_t: 0,
             end_t: 0,
-            opt_1: 0,
-            opt_2: 0,
+            lock_t: 0,
+            outcomes: Vec::new(&env),
+            totals: Vec::new(&env),
             total: 0,
-            winner: NONE,
+            winner: None,
+            claimed_votes: 0,
+            paid: 0,
+            proposed_t: 0,
+            slashed: 0,
         })
     }
     
-    // function for a voter to view their vote and count, also provides initial values for the struct
-    pub fn view_voter(env: Env, voter: Address) -> Record {
-        let key = Registry::Record(voter.clone());
-        env.storage().instance().get(&key).unwrap_or(Record {
-            selected: symbol_short!("none"),
-            votes: 0,
+    // function to get the Address of the token used for staking
+    pub fn view_token(env: Env, market_id: u32) -> Result<Address, Error> {
+        env.storage().persistent().get(&Registry::Token(market_id)).ok_or(Error::NoMarket)
+    }
+
+    // function for a voter to view their votes on each outcome, also provides initial values
+    // for the struct, with a zero position for every outcome
+    pub fn view_voter(env: Env, market_id: u32, voter: Address) -> Record {
+        let key = Registry::Record(market_id, voter.clone());
+        env.storage().persistent().get(&key).unwrap_or_else(|| Record {
+            votes: zeros(&env, Self::view_predictions(env.clone(), market_id).totals.len()),
+            total: 0,
             time: 0,
+            claimed: false,
         })
     }
 
     // internal function to view admin
-    pub fn view_admin(env: Env, admin: Address) -> AdminRecord {
-        let key = Registry::Admin(admin.clone());
-        env.storage().instance().get(&key).unwrap_or(AdminRecord {
+    pub fn view_admin(env: Env, market_id: u32, admin: Address) -> AdminRecord {
+        let key = Registry::Admin(market_id, admin.clone());
+        env.storage().persistent().get(&key).unwrap_or(AdminRecord {
             has_init: 0,
         })
     }
 
+    // function to list every current admin
+    pub fn view_admins(env: Env, market_id: u32) -> Vec<Address> {
+        env.storage().persistent().get(&Registry::Admins(market_id)).unwrap_or(Vec::new(&env))
+    }
+
+    // function to view the admin transfer waiting to be accepted, if any
+    pub fn view_pending_admin(env: Env, market_id: u32) -> Option<AdminTransfer> {
+        env.storage().persistent().get(&Registry::PendingAdmin(market_id))
+    }
+
+    // function for an admin to hand their rights to new_admin, which only takes
+    // effect once new_admin calls accept_admin(), replaces any earlier proposal
+    pub fn propose_admin(env: Env, market_id: u32, admin: Address, new_admin: Address) -> Result<(), Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        let transfer = AdminTransfer {
+            from: admin.clone(),
+            to: new_admin.clone(),
+        };
+        env.storage().persistent().set(&Registry::PendingAdmin(market_id), &transfer);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::admin_propose(&env, market_id, &admin, &new_admin);
+        Ok(())
+    }
+
+    // function for the proposed admin to accept a transfer, the proposing admin
+    // loses their rights and new_admin gains them, the proposal lapses if the
+    // proposing admin was removed in the meantime
+    pub fn accept_admin(env: Env, market_id: u32, new_admin: Address) -> Result<(), Error> {
+        new_admin.require_auth();
+        let pred = Self::load_market(&env, market_id)?;
+        let transfer = match Self::view_pending_admin(env.clone(), market_id) {
+            Some(transfer) if transfer.to == new_admin => transfer,
+            _ => return Err(Error::NoPendingAdmin),
+        };
+        if Self::view_admin(env.clone(), market_id, transfer.from.clone()).has_init == 0 {
+            return Err(Error::NoPendingAdmin);
+        }
+        env.storage().persistent().remove(&Registry::PendingAdmin(market_id));
+        Self::set_admin(&env, market_id, &transfer.from, false);
+        Self::set_admin(&env, market_id, &new_admin, true);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::admin_transfer(&env, market_id, &transfer.from, &new_admin);
+        Ok(())
+    }
+
+    // function for an admin to give admin rights to another address
+    pub fn add_admin(env: Env, market_id: u32, admin: Address, new_admin: Address) -> Result<(), Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if Self::view_admin(env.clone(), market_id, new_admin.clone()).has_init == 1 {
+            return Err(Error::AlreadyAdmin);
+        }
+        Self::set_admin(&env, market_id, &new_admin, true);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::admin_add(&env, market_id, &admin, &new_admin);
+        Ok(())
+    }
+
+    // function for an admin to take admin rights away from an address, which can
+    // be themselves, the last admin can't be removed
+    pub fn remove_admin(env: Env, market_id: u32, admin: Address, old_admin: Address) -> Result<(), Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if Self::view_admin(env.clone(), market_id, old_admin.clone()).has_init == 0 {
+            return Err(Error::AdminInvalid);
+        }
+        if Self::view_admins(env.clone(), market_id).len() <= 1 {
+            return Err(Error::LastAdmin);
+        }
+        Self::set_admin(&env, market_id, &old_admin, false);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::admin_remove(&env, market_id, &admin, &old_admin);
+        Ok(())
+    }
+
+    // function for admins to stop accepting stakes ahead of closing the market
+    pub fn lock(env: Env, market_id: u32, admin: Address) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let mut pred = Self::load_market(&env, market_id)?;
+        Self::move_status(&mut pred, MarketStatus::Locked)?;
+        Self::save_market(&env, market_id, &pred);
+
+        events::lock(&env, market_id, &admin, pred.end_t);
+        Ok(pred)
+    }
+
+    // function for admins to set how long after end_t the market stays locked
+    // before it can be closed, only while the market is still open
+    pub fn set_lock_period(env: Env, market_id: u32, admin: Address, lock_t: u64) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let mut pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        claim_deadline(pred.end_t, lock_t)?;
+        pred.lock_t = lock_t;
+        Self::save_market(&env, market_id, &pred);
+
+        events::lock_period(&env, market_id, &admin, lock_t);
+        Ok(pred)
+    }
+
+    // function for admins to void the market, every staker can then refund()
+    // their stake, allowed any time before the market is resolved
+    pub fn cancel(env: Env, market_id: u32, admin: Address) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status == MarketStatus::Disputed {
+            return Err(Error::MarketDisputed);
+        }
+        Self::cancel_market(&env, market_id, &admin, pred)
+    }
+
     // function for admins to close the market, specifying a winner
-    // input should either be Symbol "OPT1" or Symbol "OPT2", else remains as "none"
-    pub fn close(env: Env, admin: Address, winner: Symbol) -> PredictionRecord {
-        admin.require_auth();
-        let mut pred = Self::view_predictions(env.clone());
-        let admin_cred = Self::view_admin(env.clone(), admin.clone());
-        if admin_cred.has_init == 0 {
-            panic!("Admin invalid");
+    // input is the index of the winning outcome, as given to create_market()
+    // can only be called once end_t and the lock period after it have passed,
+    // and only for markets without an oracle
+    pub fn close(env: Env, market_id: u32, admin: Address, winner: u32) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if Self::view_oracle(env.clone(), market_id).is_some() {
+            return Err(Error::OracleMarket);
+        }
+        if Self::view_scalar(env.clone(), market_id).is_some() {
+            return Err(Error::ScalarMarket);
+        }
+        if env.ledger().timestamp() <= pred.end_t + pred.lock_t {
+            return Err(Error::MarketRunning);
+        }
+        Self::settle(&env, market_id, &admin, pred, winner)
+    }
+
+    // function for admins to close the market with the pool split between outcomes,
+    // for ties and ambiguous results. weights are in basis points, one per outcome,
+    // and add up to WEIGHT_TOTAL, e.g. 5000 and 5000 for a 50/50 split
+    // each outcome's part is shared by its stakers pro rata, same rules as close()
+    // otherwise. in an LMSR market each share redeems for its outcome's weight
+    pub fn close_split(env: Env, market_id: u32, admin: Address, weights: Vec<u32>) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if Self::view_oracle(env.clone(), market_id).is_some() {
+            return Err(Error::OracleMarket);
+        }
+        if Self::view_scalar(env.clone(), market_id).is_some() {
+            return Err(Error::ScalarMarket);
+        }
+        if Self::view_dispute(env.clone(), market_id).is_some() {
+            return Err(Error::DisputeMarket);
+        }
+        if env.ledger().timestamp() <= pred.end_t + pred.lock_t {
+            return Err(Error::MarketRunning);
+        }
+        if weights.len() != pred.outcomes.len() || weights.iter().map(u64::from).sum::<u64>() != WEIGHT_TOTAL as u64 {
+            return Err(Error::InvalidWeights);
+        }
+
+        let mut split = Vec::new(&env);
+        for weight in weights.iter() {
+            split.push_back(weight as i128);
+        }
+        Self::finish_split(&env, market_id, &admin, pred, &split)
+    }
+
+    // function anyone can call to close an oracle market with the winner reported
+    // by the oracle, same rules as close() otherwise
+    pub fn resolve(env: Env, market_id: u32) -> Result<PredictionRecord, Error> {
+        let pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_oracle(env.clone(), market_id).ok_or(Error::NoOracle)?;
+        if env.ledger().timestamp() <= pred.end_t + pred.lock_t {
+            return Err(Error::MarketRunning);
+        }
+        let oracle = OracleClient::new(&env, &config.oracle);
+        let winner = oracle.outcome(&config.market_id).ok_or(Error::OracleNoOutcome)?;
+        Self::settle(&env, market_id, &config.oracle, pred, winner)
+    }
+
+    // function to view the oracle the market is resolved by, if any
+    pub fn view_oracle(env: Env, market_id: u32) -> Option<OracleConfig> {
+        env.storage().persistent().get(&Registry::Oracle(market_id))
+    }
+
+    // function for stakers to claim the earnings based on vote once market is closed
+    // payouts are made from this contract's balance of the market's token, which
+    // can back many markets, each market only ever pays out what was staked in it
+    // returns the amount paid to the user, each record can only be claimed once
+    // in a market with share tokens the user's winning tokens are burned instead
+    pub fn claim(env: Env, market_id: u32, user: Address) -> Result<i128, Error> {
+        user.require_auth();
+
+        let mut poll = Self::load_market(&env, market_id)?;
+        if let Some(tokens) = Self::view_share_tokens(env.clone(), market_id) {
+            if poll.status != MarketStatus::Resolved {
+                return Err(Error::MarketNotResolved);
+            }
+            return Self::redeem_shares(&env, market_id, &user, poll, &tokens);
+        }
+        let mut records = Self::view_voter(env.clone(), market_id, user.clone());
+        let lmsr = Self::view_lmsr(env.clone(), market_id).is_some();
+        let split = Self::view_split(env.clone(), market_id);
+        let payout = Self::payout(&poll, &records, lmsr, split.as_ref())?;
+
+        // mark the record as paid out before sending anything
+        records.claimed = true;
+        poll.claimed_votes += match (&split, poll.winner) {
+            (Some(split), _) => Self::paying_votes(&records.votes, split),
+            (None, Some(winner)) => records.votes.get(winner).unwrap_or(0),
+            _ => 0,
+        };
+        Self::save_voter(&env, market_id, &user, &records, &poll);
+        Self::pay(&env, market_id, &mut poll, &user, payout)?;
+
+        events::claim(&env, market_id, &user, payout);
+        Ok(payout)
+    }
+
+    // function for stakers to get their stake back once the market is cancelled
+    // returns the amount refunded, each record can only be refunded once
+    // in a market with share tokens every outcome token the user holds is burned instead
+    pub fn refund(env: Env, market_id: u32, user: Address) -> Result<i128, Error> {
+        user.require_auth();
+
+        let mut poll = Self::load_market(&env, market_id)?;
+        if let Some(tokens) = Self::view_share_tokens(env.clone(), market_id) {
+            if poll.status != MarketStatus::Cancelled {
+                return Err(Error::MarketNotCancelled);
+            }
+            return Self::redeem_shares(&env, market_id, &user, poll, &tokens);
+        }
+        let mut records = Self::view_voter(env.clone(), market_id, user.clone());
+        let amount = Self::refundable(&poll, &records)?;
+
+        // mark the record as paid out before sending anything
+        records.claimed = true;
+        poll.claimed_votes += records.total;
+        Self::save_voter(&env, market_id, &user, &records, &poll);
+        Self::pay(&env, market_id, &mut poll, &user, amount)?;
+
+        events::refund(&env, market_id, &user, amount);
+        Ok(amount)
+    }
+
+    // function to view the amount claim() or refund() would pay the user right now,
+    // 0 if there is nothing to claim
+    pub fn view_claimable(env: Env, market_id: u32, user: Address) -> i128 {
+        let poll = Self::view_predictions(env.clone(), market_id);
+        if let Some(tokens) = Self::view_share_tokens(env.clone(), market_id) {
+            return Self::shares_owed(&env, market_id, &poll, &tokens, &user).map_or(0, |owed| owed.0);
+        }
+        let records = Self::view_voter(env.clone(), market_id, user);
+        if poll.status == MarketStatus::Cancelled {
+            Self::refundable(&poll, &records).unwrap_or(0)
         }
         else {
-            if pred.winner != NONE {
-                panic!("Predictions already closed");
+            let lmsr = Self::view_lmsr(env.clone(), market_id).is_some();
+            let split = Self::view_split(env, market_id);
+            Self::payout(&poll, &records, lmsr, split.as_ref()).unwrap_or(0)
+        }
+    }
+
+    // function to view the part of the pool each outcome was given, for markets
+    // resolved by a split rather than a single winner
+    pub fn view_split(env: Env, market_id: u32) -> Option<Vec<i128>> {
+        env.storage().persistent().get(&Registry::Split(market_id))
+    }
+
+    // function anyone can call to keep a long-running market and the given voters'
+    // records alive, extends their TTL to the end of the claim window
+    pub fn bump(env: Env, market_id: u32, voters: Vec<Address>) -> Result<(), Error> {
+        let pred = Self::load_market(&env, market_id)?;
+        Self::extend_market(&env, market_id, &pred);
+        let ttl = ttl_ledgers(&env, &pred);
+        for voter in voters.iter() {
+            let key = Registry::Record(market_id, voter);
+            if env.storage().persistent().has(&key) {
+                env.storage().persistent().extend_ttl(&key, ttl.saturating_sub(BUMP_MARGIN), ttl);
             }
+        }
+        Ok(())
+    }
 
-            if winner.clone() == OPT1 {
-                log!(&env, "Winner OPT1: {}", pred.opt_1);
+    // internal function to store the market and keep its data alive until the
+    // end of the claim window
+    fn save_market(env: &Env, market_id: u32, pred: &PredictionRecord) {
+        env.storage().persistent().set(&Registry::Prediction(market_id), pred);
+        Self::extend_market(env, market_id, pred);
+    }
+
+    // internal function to keep the market's entries and the contract instance alive
+    // until the end of the claim window, voter records are extended separately
+    // the instance TTL is only ever raised, so it outlives every market's window
+    fn extend_market(env: &Env, market_id: u32, pred: &PredictionRecord) {
+        let ttl = ttl_ledgers(env, pred);
+        let threshold = ttl.saturating_sub(BUMP_MARGIN);
+        env.storage().instance().extend_ttl(threshold, ttl);
+
+        let keys = [
+            Registry::Prediction(market_id),
+            Registry::Token(market_id),
+            Registry::Oracle(market_id),
+            Registry::Dispute(market_id),
+            Registry::Challenge(market_id),
+            Registry::Admins(market_id),
+            Registry::PendingAdmin(market_id),
+            Registry::Lmsr(market_id),
+            Registry::ShareTokens(market_id),
+            Registry::ExitFee(market_id),
+            Registry::Scalar(market_id),
+            Registry::Split(market_id),
+        ];
+        for key in keys.iter() {
+            if env.storage().persistent().has(key) {
+                env.storage().persistent().extend_ttl(key, threshold, ttl);
             }
-            else if winner.clone() == OPT2 {
-                log!(&env, "Winner OPT2: {}", pred.opt_2);
+        }
+        for admin in Self::view_admins(env.clone(), market_id).iter() {
+            env.storage().persistent().extend_ttl(&Registry::Admin(market_id, admin), threshold, ttl);
+        }
+    }
+
+    // internal function to grant or revoke admin rights, keeps Admin(market_id, address)
+    // and the Admins list in step
+    fn set_admin(env: &Env, market_id: u32, admin: &Address, on: bool) {
+        let key = Registry::Admin(market_id, admin.clone());
+        let mut admins = Self::view_admins(env.clone(), market_id);
+        if on {
+            env.storage().persistent().set(&key, &AdminRecord { has_init: 1 });
+            if !admins.contains(admin) {
+                admins.push_back(admin.clone());
             }
-            else {
-                panic!("Invalid winner");
+        }
+        else {
+            env.storage().persistent().remove(&key);
+            if let Some(i) = admins.first_index_of(admin) {
+                admins.remove(i);
             }
+        }
+        env.storage().persistent().set(&Registry::Admins(market_id), &admins);
+    }
 
-            pred.has_init = 0;
-            pred.winner = winner;
-            env.storage().instance().set(&Registry::Prediction, &pred);
-            env.storage().instance().extend_ttl(100, 100);
-            return pred;
+    // internal function to store a voter's record in persistent storage, it lives
+    // as long as the market does so it can still be claimed or refunded
+    fn save_voter(env: &Env, market_id: u32, voter: &Address, records: &Record, pred: &PredictionRecord) {
+        let ttl = ttl_ledgers(env, pred);
+        let key = Registry::Record(market_id, voter.clone());
+        env.storage().persistent().set(&key, records);
+        env.storage().persistent().extend_ttl(&key, ttl.saturating_sub(BUMP_MARGIN), ttl);
+    }
+
+    // internal function to load the market, fails if there is no market with this id
+    fn load_market(env: &Env, market_id: u32) -> Result<PredictionRecord, Error> {
+        Self::check_version(env)?;
+        let pred = Self::view_predictions(env.clone(), market_id);
+        if pred.has_init == 0 {
+            return Err(Error::NoMarket);
         }
+        Ok(pred)
     }
 
-    // function for stakers to claim the earnings based on vote once market is closed
-    // returns the amount given to the user
-    // contract: the prediction contract Address, used for sending XLM to stakers
-    pub fn claim(env: Env, user: Address, xlm: Address, contract: Address) {
-        // verify caller with stored Record
-        let key = Registry::Record(user.clone());
-        user.require_auth();
+    // internal function to check storage has been migrated to SCHEMA_VERSION
+    fn check_version(env: &Env) -> Result<(), Error> {
+        if Self::view_version(env.clone()) < SCHEMA_VERSION {
+            return Err(Error::MigrationPending);
+        }
+        Ok(())
+    }
+
+    // internal function to check the caller is the admin of the contract itself
+    fn check_contract_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+        admin.require_auth();
+        let stored = Self::view_contract_admin(env.clone()).ok_or(Error::NotInit)?;
+        if stored != *admin {
+            return Err(Error::AdminInvalid);
+        }
+        Ok(())
+    }
 
-        let poll = Self::view_predictions(env.clone());
-        let user_record = env.storage().instance().get(&key).unwrap_or(0);
-        
-        // if user has a record and market is open, not valid
-        if user_record == 0 {
-            panic!("No user record");
+    // internal function to check the caller is a registered admin
+    fn check_admin(env: &Env, market_id: u32, admin: &Address) -> Result<(), Error> {
+        admin.require_auth();
+        if Self::view_admin(env.clone(), market_id, admin.clone()).has_init == 0 {
+            return Err(Error::AdminInvalid);
         }
-        else if poll.has_init == 0 {
-            panic!("Market not closed");
+        Ok(())
+    }
+
+    // internal function to work out what a record is owed from a resolved market
+    // in an LMSR market every winning share redeems 1:1 instead, and in a market
+    // resolved by a split every outcome pays out its part, see split_winnings
+    fn payout(poll: &PredictionRecord, records: &Record, lmsr: bool, split: Option<&Vec<i128>>) -> Result<i128, Error> {
+        // if user has no record or the market has no winner yet, not valid
+        if records.total == 0 {
+            return Err(Error::NoUserRecord);
         }
-        else if poll.winner == NONE {
-            panic!("Market not closed");
+        if records.claimed {
+            return Err(Error::AlreadyClaimed);
+        }
+        if let (MarketStatus::Resolved, Some(split)) = (poll.status, split) {
+            return Self::split_winnings(poll, &records.votes, split);
+        }
+        let winner = match (poll.status, poll.winner) {
+            (MarketStatus::Resolved, Some(winner)) => winner,
+            _ => return Err(Error::MarketNotResolved),
+        };
+        // only the part of the position staked on the winner is paid out
+        let stake = records.votes.get(winner).unwrap_or(0);
+        if stake == 0 {
+            return Err(Error::NothingToClaim);
+        }
+        Self::winnings(poll, winner, stake, lmsr)
+    }
+
+    // internal function to work out the payout for stake winning votes, or shares
+    // in an LMSR market, which redeem 1:1
+    fn winnings(poll: &PredictionRecord, winner: u32, stake: u64, lmsr: bool) -> Result<i128, Error> {
+        if lmsr {
+            return Ok(stake as i128);
+        }
+
+        // allocate payments from current pool, based on user's vote
+        // proportion of the winning votes, since each vote means a certain amount bet
+        // the last winner to claim also takes the rounding dust, see payout::remainder
+        let winning_votes = poll.totals.get(winner).unwrap_or(0);
+        let pool = poll.total as i128 + poll.slashed;
+        let amount = if poll.claimed_votes + stake == winning_votes {
+            payout::remainder(pool, poll.paid)
         }
         else {
-            // TokenClient with the XLM Contract address
-            let client = token::TokenClient::new(&env, &xlm);
-            let records = Self::view_voter(env.clone(), user.clone());
-            
-            // allocate XLM payments from current pool, based on user's vote 
-            // proportion of total, since each vote means a certain amount bet
-            // formula = voter's full vote amount + proportion of votes from the losing pool
-            let opt1_votes: f64 = poll.opt_1 as f64;
-            let opt2_votes: f64 = poll.opt_2 as f64;
-            let user_votes: f64 = records.votes as f64;
-
-            if poll.winner == records.selected {
-                if records.selected == OPT1 {
-                    let ratio = user_votes / opt1_votes;
-                    let mut payout: f64 = ratio * opt2_votes;
-                    payout += user_votes;
-                    let final_pay: i128 = payout as i128;
-                    client.transfer(&contract, &user, &final_pay);
+            payout::share(stake as i128, winning_votes as i128, pool)
+        };
+        amount.ok_or(Error::PayoutOverflow)
+    }
+
+    // internal function to work out the payout for votes on each outcome of a market
+    // resolved by a split, every outcome's part is shared pro rata by its stakes
+    // the last stake to claim takes the rounding dust, as in payout()
+    fn split_winnings(poll: &PredictionRecord, votes: &Vec<u64>, split: &Vec<i128>) -> Result<i128, Error> {
+        let stake = Self::paying_votes(votes, split);
+        if stake == 0 {
+            return Err(Error::NothingToClaim);
+        }
+        let pool: i128 = split.iter().sum();
+        if poll.claimed_votes + stake == Self::paying_votes(&poll.totals, split) {
+            return payout::remainder(pool, poll.paid).ok_or(Error::PayoutOverflow);
+        }
+        let mut amount: i128 = 0;
+        for (i, part) in split.iter().enumerate() {
+            let held = votes.get(i as u32).unwrap_or(0);
+            if held == 0 || part == 0 {
+                continue;
+            }
+            let staked = poll.totals.get(i as u32).unwrap_or(0);
+            let owed = payout::part(held as i128, staked as i128, part).ok_or(Error::PayoutOverflow)?;
+            amount = amount.checked_add(owed).ok_or(Error::PayoutOverflow)?;
+        }
+        Ok(amount)
+    }
+
+    // internal function to count the votes on outcomes that were given part of the pool
+    fn paying_votes(votes: &Vec<u64>, split: &Vec<i128>) -> u64 {
+        let mut paying = 0;
+        for (held, part) in votes.iter().zip(split.iter()) {
+            if part > 0 {
+                paying += held;
+            }
+        }
+        paying
+    }
+
+    // internal function to set the winner once the caller and deadline are checked,
+    // by is the admin or oracle resolving the market
+    // with a dispute window the winner is only proposed, see dispute.rs
+    fn settle(env: &Env, market_id: u32, by: &Address, mut pred: PredictionRecord, winner: u32) -> Result<PredictionRecord, Error> {
+        let staked = pred.totals.get(winner).ok_or(Error::InvalidOutcome)?;
+        if staked > 0 && Self::view_dispute(env.clone(), market_id).is_some() {
+            Self::move_status(&mut pred, MarketStatus::Proposed)?;
+            pred.winner = Some(winner);
+            pred.proposed_t = env.ledger().timestamp();
+            Self::save_market(env, market_id, &pred);
+
+            events::propose(env, market_id, by, winner);
+            return Ok(pred);
+        }
+        Self::finish(env, market_id, by, pred, winner)
+    }
+
+    // internal function to make winner the final outcome
+    // if nobody staked on the winner there is no one to pay, so the market
+    // is cancelled instead and every staker can refund()
+    fn finish(env: &Env, market_id: u32, by: &Address, mut pred: PredictionRecord, winner: u32) -> Result<PredictionRecord, Error> {
+        let staked = pred.totals.get(winner).ok_or(Error::InvalidOutcome)?;
+        log!(env, "Winner {}: {}", winner, staked);
+        if staked == 0 {
+            return Self::cancel_market(env, market_id, by, pred);
+        }
+
+        Self::move_status(&mut pred, MarketStatus::Resolved)?;
+        pred.winner = Some(winner);
+        Self::save_market(env, market_id, &pred);
+
+        events::resolve(env, market_id, by, winner, &pred.totals);
+        Ok(pred)
+    }
+
+    // internal function to resolve the market by splitting the pool between outcomes
+    // in proportion to weights, one per outcome. outcomes nobody staked on get
+    // nothing and the others share their weight, if none of them was staked on
+    // there is no one to pay and the market is cancelled instead
+    // an LMSR market has no pool to split, each share is owed its outcome's part of
+    // one token instead and the market maker keeps the rest
+    fn finish_split(env: &Env, market_id: u32, by: &Address, mut pred: PredictionRecord, weights: &Vec<i128>) -> Result<PredictionRecord, Error> {
+        let mut backed: i128 = 0;
+        let mut last = None;
+        for (i, (weight, staked)) in weights.iter().zip(pred.totals.iter()).enumerate() {
+            if weight > 0 && staked > 0 {
+                backed = backed.checked_add(weight).ok_or(Error::PayoutOverflow)?;
+                last = Some(i as u32);
+            }
+        }
+        let last = match last {
+            Some(last) => last,
+            None => return Self::cancel_market(env, market_id, by, pred),
+        };
+
+        let mut split = Vec::new(env);
+        if Self::view_lmsr(env.clone(), market_id).is_some() {
+            let total: i128 = weights.iter().sum();
+            for (weight, staked) in weights.iter().zip(pred.totals.iter()) {
+                split.push_back((staked as i128).checked_mul(weight).ok_or(Error::PayoutOverflow)? / total);
+            }
+        }
+        else {
+            let pool = pred.total as i128 + pred.slashed;
+            let mut left = pool;
+            for (weight, staked) in weights.iter().zip(pred.totals.iter()) {
+                let part = if weight > 0 && staked > 0 {
+                    pool.checked_mul(weight).ok_or(Error::PayoutOverflow)? / backed
                 }
                 else {
-                    let ratio = user_votes / opt2_votes;
-                    let mut payout: f64 = ratio * opt1_votes;
-                    payout += user_votes;
-                    let final_pay: i128 = payout as i128;
-                    client.transfer(&contract, &user, &final_pay);
-                }
-                
-            }
-            else {
-                panic!("No valid claimings");
+                    0
+                };
+                split.push_back(part);
+                left -= part;
             }
+            // the rounding dust goes to the last outcome paid
+            split.set(last, split.get(last).unwrap_or(0) + left);
+        }
+
+        Self::move_status(&mut pred, MarketStatus::Resolved)?;
+        pred.winner = None;
+        env.storage().persistent().set(&Registry::Split(market_id), &split);
+        Self::save_market(env, market_id, &pred);
+
+        events::split(env, market_id, by, &split);
+        Ok(pred)
+    }
+
+    // internal function to move the market to Cancelled once the caller is checked
+    fn cancel_market(env: &Env, market_id: u32, admin: &Address, mut pred: PredictionRecord) -> Result<PredictionRecord, Error> {
+        Self::move_status(&mut pred, MarketStatus::Cancelled)?;
+        Self::save_market(env, market_id, &pred);
+
+        events::cancel(env, market_id, admin, pred.total);
+        Ok(pred)
+    }
+
+    // internal function to work out what a record gets back from a cancelled market,
+    // its stake plus a pro rata part of any exit fees kept, the last refund takes the dust
+    fn refundable(poll: &PredictionRecord, records: &Record) -> Result<i128, Error> {
+        if poll.status != MarketStatus::Cancelled {
+            return Err(Error::MarketNotCancelled);
+        }
+        if records.total == 0 {
+            return Err(Error::NoUserRecord);
+        }
+        if records.claimed {
+            return Err(Error::AlreadyClaimed);
+        }
+        let pool = poll.total as i128 + poll.slashed;
+        let amount = if poll.claimed_votes + records.total == poll.total {
+            payout::remainder(pool, poll.paid)
+        }
+        else {
+            payout::share(records.total as i128, poll.total as i128, pool)
+        };
+        amount.ok_or(Error::PayoutOverflow)
+    }
+
+    // internal function to work out the most the market can ever pay out to stakers,
+    // the pool for parimutuel markets. an LMSR market owes 1:1 for winning shares,
+    // each outcome's part of a split, or what was paid in if it is cancelled
+    fn owed(env: &Env, market_id: u32, poll: &PredictionRecord) -> Result<i128, Error> {
+        let pool = poll.total as i128 + poll.slashed;
+        if poll.status == MarketStatus::Cancelled || Self::view_lmsr(env.clone(), market_id).is_none() {
+            return Ok(pool);
+        }
+        match (poll.status, poll.winner, Self::view_split(env.clone(), market_id)) {
+            (MarketStatus::Resolved, _, Some(split)) => Ok(split.iter().sum()),
+            (MarketStatus::Resolved, Some(winner), None) => Ok(poll.totals.get(winner).unwrap_or(0) as i128),
+            _ => Err(Error::MarketNotResolved),
         }
     }
-}
\ No newline at end of file
+
+    // internal function every claim and refund pays out through, adds amount to what
+    // the market has paid and sends it to user. fails rather than pay out more than
+    // the market owes, whatever the payout math or a share token says
+    fn pay(env: &Env, market_id: u32, poll: &mut PredictionRecord, user: &Address, amount: i128) -> Result<(), Error> {
+        let paid = poll.paid.checked_add(amount).ok_or(Error::PayoutOverflow)?;
+        if amount < 0 || paid > Self::owed(env, market_id, poll)? {
+            return Err(Error::PayoutOverflow);
+        }
+        poll.paid = paid;
+        Self::save_market(env, market_id, poll);
+
+        // TokenClient with the stored staking token address
+        let client = token::TokenClient::new(env, &Self::view_token(env.clone(), market_id)?);
+        client.transfer(&env.current_contract_address(), user, &amount);
+        Ok(())
+    }
+
+    // internal function to move the market along its lifecycle, fails on
+    // any transition not allowed by MarketStatus::can_move_to
+    fn move_status(pred: &mut PredictionRecord, next: MarketStatus) -> Result<(), Error> {
+        if !pred.status.can_move_to(next) {
+            return Err(Error::InvalidStatusChange);
+        }
+        pred.status = next;
+        Ok(())
+    }
+}
+
+mod test;
@@ -0,0 +1,208 @@
+use soroban_sdk::{Address, contractimpl, contracttype, Env, token, Vec};
+
+use crate::fixed::{self, SCALE};
+use crate::{events, Error, MarketStatus, PredictionRecord, Registry, VoteContract, VoteContractClient};
+
+// LMSR pricing mode: instead of sharing the pool between winners, the contract acts
+// as a market maker on Hanson's logarithmic market scoring rule. shares are bought
+// at a price set by how many of each are outstanding, and winning shares redeem 1:1
+// positions use the same Record and PredictionRecord as stakes do, with shares in
+// votes and totals, and tokens paid in Record.total and PredictionRecord.total
+
+// largest liquidity parameter accepted, keeps b * ln(sum) well inside an i128
+const MAX_LIQUIDITY: u64 = 1_000_000_000_000_000_000;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// struct for the LMSR pricing of a market, set by an admin with set_lmsr()
+// b: the liquidity parameter, the higher it is the less prices move per share
+// provider: the admin who paid the subsidy, and gets back whatever isn't owed to stakers
+// subsidy: the market maker's worst case loss b * ln(outcomes), paid in by the provider
+pub struct LmsrConfig {
+    pub b: u64,
+    pub provider: Address,
+    pub subsidy: i128,
+    // set once reclaim_subsidy() has paid the provider
+    pub reclaimed: bool,
+}
+
+// cost function C(q) = b * ln(sum of e^(q_i / b)) for outstanding shares q, scaled by SCALE
+// worked out as max(q) + b * ln(sum of e^((q_i - max(q)) / b)) so every exponent is <= 0
+pub fn cost(q: &Vec<u64>, b: u64) -> i128 {
+    let max = q.iter().max().unwrap_or(0) as i128;
+    let b = b as i128;
+    let mut sum = 0;
+    for qi in q.iter() {
+        sum += fixed::exp_neg((qi as i128 - max) * SCALE / b);
+    }
+    max * SCALE + b * fixed::ln(sum)
+}
+
+// price of one share of each outcome, scaled by SCALE, they add up to about SCALE
+pub fn prices(q: &Vec<u64>, b: u64) -> Vec<i128> {
+    let max = q.iter().max().unwrap_or(0) as i128;
+    let b = b as i128;
+    let mut weights = Vec::new(q.env());
+    let mut sum = 0;
+    for qi in q.iter() {
+        let weight = fixed::exp_neg((qi as i128 - max) * SCALE / b);
+        weights.push_back(weight);
+        sum += weight;
+    }
+    let mut prices = Vec::new(q.env());
+    for weight in weights.iter() {
+        prices.push_back(weight * SCALE / sum);
+    }
+    prices
+}
+
+// tokens to pay for shares more of outcome given q outstanding, C(q') - C(q) rounded
+// up so rounding never comes out of the subsidy
+pub fn buy_cost(q: &Vec<u64>, b: u64, outcome: u32, shares: u64) -> Option<i128> {
+    let mut after = q.clone();
+    let held = after.get(outcome)?;
+    after.set(outcome, held.checked_add(shares)?);
+    let diff = cost(&after, b) - cost(q, b);
+    Some((diff + SCALE - 1) / SCALE)
+}
+
+// subsidy covering the market maker's worst case loss b * ln(outcomes), rounded up
+// with a unit to spare for rounding in the cost function
+pub fn subsidy(b: u64, outcomes: u32) -> i128 {
+    let loss = b as i128 * fixed::ln(outcomes as i128 * SCALE);
+    (loss + SCALE - 1) / SCALE + 1
+}
+
+#[contractimpl]
+impl VoteContract {
+    // function for admins to switch a market to LMSR pricing with liquidity b, only
+    // before anything is staked. the admin pays the subsidy into the contract
+    // record_votes() is then replaced by buy_shares()
+    pub fn set_lmsr(env: Env, market_id: u32, admin: Address, b: u64) -> Result<LmsrConfig, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if pred.total != 0 || Self::view_lmsr(env.clone(), market_id).is_some() {
+            return Err(Error::MarketNotEmpty);
+        }
+        if Self::view_scalar(env.clone(), market_id).is_some() {
+            return Err(Error::ScalarMarket);
+        }
+        if b == 0 || b > MAX_LIQUIDITY {
+            return Err(Error::InvalidLiquidity);
+        }
+
+        let config = LmsrConfig {
+            b,
+            provider: admin.clone(),
+            subsidy: subsidy(b, pred.outcomes.len()),
+            reclaimed: false,
+        };
+        env.storage().persistent().set(&Registry::Lmsr(market_id), &config);
+        Self::extend_market(&env, market_id, &pred);
+
+        let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+        client.transfer(&admin, &env.current_contract_address(), &config.subsidy);
+
+        events::lmsr(&env, market_id, &admin, b, config.subsidy);
+        Ok(config)
+    }
+
+    // function to view the market's LMSR pricing, if any
+    pub fn view_lmsr(env: Env, market_id: u32) -> Option<LmsrConfig> {
+        env.storage().persistent().get(&Registry::Lmsr(market_id))
+    }
+
+    // function to view the current price of a share of each outcome, scaled by 10^18
+    pub fn view_prices(env: Env, market_id: u32) -> Result<Vec<i128>, Error> {
+        let pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_lmsr(env, market_id).ok_or(Error::NotLmsr)?;
+        Ok(prices(&pred.totals, config.b))
+    }
+
+    // function to view what buy_shares() would charge right now
+    pub fn view_quote(env: Env, market_id: u32, outcome: u32, shares: u64) -> Result<i128, Error> {
+        let pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_lmsr(env, market_id).ok_or(Error::NotLmsr)?;
+        buy_cost(&pred.totals, config.b, outcome, shares).ok_or(Error::InvalidOutcome)
+    }
+
+    // function to buy shares of an outcome in an LMSR market, the cost is transferred
+    // from the user in the same call and fails if it is over max_cost
+    // returns the cost paid, each winning share redeems 1:1 through claim()
+    pub fn buy_shares(env: Env, market_id: u32, user: Address, outcome: u32, shares: u64, max_cost: i128) -> Result<i128, Error> {
+        user.require_auth();
+        if shares == 0 {
+            return Err(Error::InvalidVotes);
+        }
+        let mut pred = Self::load_market(&env, market_id)?;
+        let config = Self::view_lmsr(env.clone(), market_id).ok_or(Error::NotLmsr)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if env.ledger().timestamp() > pred.end_t {
+            return Err(Error::MarketEnded);
+        }
+        let cost = buy_cost(&pred.totals, config.b, outcome, shares).ok_or(Error::InvalidOutcome)?;
+        if cost > max_cost {
+            return Err(Error::CostTooHigh);
+        }
+        let paid = u64::try_from(cost).map_err(|_| Error::PayoutOverflow)?;
+
+        let mut records = Self::view_voter(env.clone(), market_id, user.clone());
+        let held = records.votes.get(outcome).unwrap_or(0);
+        records.votes.set(outcome, held + shares);
+        records.total += paid;
+        records.time = env.ledger().timestamp();
+        let outstanding = pred.totals.get(outcome).unwrap_or(0);
+        pred.totals.set(outcome, outstanding + shares);
+        pred.total += paid;
+
+        let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+        client.transfer(&user, &env.current_contract_address(), &cost);
+        Self::mint_shares(&env, market_id, &user, outcome, shares);
+
+        Self::save_voter(&env, market_id, &user, &records, &pred);
+        Self::save_market(&env, market_id, &pred);
+
+        events::buy(&env, market_id, &user, outcome, shares, cost);
+        Ok(cost)
+    }
+
+    // function for the provider to take back what the market maker holds beyond what
+    // it owes stakers, once the market is resolved or cancelled. returns the amount
+    pub fn reclaim_subsidy(env: Env, market_id: u32, provider: Address) -> Result<i128, Error> {
+        provider.require_auth();
+        let pred = Self::load_market(&env, market_id)?;
+        let mut config = Self::view_lmsr(env.clone(), market_id).ok_or(Error::NotLmsr)?;
+        if config.provider != provider {
+            return Err(Error::AdminInvalid);
+        }
+        if config.reclaimed {
+            return Err(Error::AlreadyClaimed);
+        }
+        let amount = Self::lmsr_surplus(&env, market_id, &pred, &config)?;
+
+        config.reclaimed = true;
+        env.storage().persistent().set(&Registry::Lmsr(market_id), &config);
+        Self::extend_market(&env, market_id, &pred);
+
+        let client = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?);
+        client.transfer(&env.current_contract_address(), &provider, &amount);
+
+        events::reclaim(&env, market_id, &provider, amount);
+        Ok(amount)
+    }
+
+    // internal function to work out what an LMSR market holds beyond what it owes
+    // stakers, see owed()
+    fn lmsr_surplus(env: &Env, market_id: u32, pred: &PredictionRecord, config: &LmsrConfig) -> Result<i128, Error> {
+        if !matches!(pred.status, MarketStatus::Resolved | MarketStatus::Cancelled) {
+            return Err(Error::MarketNotResolved);
+        }
+        let owed = Self::owed(env, market_id, pred)?;
+        Ok((config.subsidy + pred.total as i128 + pred.slashed - owed).max(0))
+    }
+}
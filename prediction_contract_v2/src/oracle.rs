@@ -0,0 +1,19 @@
+use soroban_sdk::{Address, contractclient, contracttype, Env};
+
+#[contracttype]
+#[derive(Clone)]
+// struct for the oracle a market is resolved by, given to create_market()
+// oracle: the Address of a contract implementing Oracle
+// market_id: the id the oracle knows this market by
+pub struct OracleConfig {
+    pub oracle: Address,
+    pub market_id: u64,
+}
+
+// interface a resolver contract has to implement, VoteContract calls it through
+// the generated OracleClient once the market can be closed
+#[contractclient(name = "OracleClient")]
+pub trait Oracle {
+    // index of the winning outcome for market_id, None until the oracle knows it
+    fn outcome(env: Env, market_id: u64) -> Option<u32>;
+}
@@ -0,0 +1,88 @@
+use soroban_sdk::{Address, contractimpl, contracttype, Env, String, vec};
+
+use crate::{Error, PredictionRecord, Registry, VoteContract, VoteContractClient};
+
+// scalar markets: the question is a number, e.g. the total damages from a storm,
+// and stakes go long or short on it between a lower and an upper bound
+// the market is an ordinary two outcome market, outcome LONG and outcome SHORT
+// once the value is reported the pool is split linearly: the long side gets
+// (value - lower) / (upper - lower) of it and the short side the rest, values
+// past a bound count as the bound. stakers share their side's part pro rata
+// the long side's part is rounded down, the dust goes to the short side
+
+// outcome index of long positions, paid more the higher the value
+pub const LONG: u32 = 0;
+// outcome index of short positions, paid more the lower the value
+pub const SHORT: u32 = 1;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// struct for the range of a scalar market, given to create_scalar_market()
+pub struct ScalarConfig {
+    pub lower: i128,
+    pub upper: i128,
+    // the value reported by close_scalar(), None until then
+    pub value: Option<i128>,
+}
+
+// the long side's part of pool for value, pool * (value - lower) / (upper - lower)
+// rounded down. the distances are taken as u128 so the full i128 range fits, and
+// scaled down first only if multiplying by pool would overflow
+fn long_part(config: &ScalarConfig, value: i128, pool: i128) -> Option<i128> {
+    let clamped = value.clamp(config.lower, config.upper);
+    let range = config.upper.abs_diff(config.lower);
+    let above = clamped.abs_diff(config.lower);
+    let pool = u128::try_from(pool).ok()?;
+    let part = match pool.checked_mul(above) {
+        Some(product) => product / range,
+        None => {
+            let scale = range / (u128::MAX / pool) + 1;
+            pool.checked_mul(above / scale)? / (range / scale)
+        }
+    };
+    i128::try_from(part).ok()
+}
+
+#[contractimpl]
+impl VoteContract {
+    // called by anyone to open a scalar market on a value between lower and upper,
+    // same as create_market() otherwise. stakes go on LONG or SHORT
+    // returns the market id
+    pub fn create_scalar_market(env: Env, admin: Address, token: Address, duration: u64, lower: i128, upper: i128) -> Result<u32, Error> {
+        if lower >= upper {
+            return Err(Error::InvalidBounds);
+        }
+        let outcomes = vec![&env, String::from_str(&env, "long"), String::from_str(&env, "short")];
+        let market_id = Self::create_market(env.clone(), admin, token, duration, outcomes, None)?;
+
+        let config = ScalarConfig { lower, upper, value: None };
+        env.storage().persistent().set(&Registry::Scalar(market_id), &config);
+        Self::extend_market(&env, market_id, &Self::view_predictions(env.clone(), market_id));
+        Ok(market_id)
+    }
+
+    // function to view the market's range and reported value, if it is scalar
+    pub fn view_scalar(env: Env, market_id: u32) -> Option<ScalarConfig> {
+        env.storage().persistent().get(&Registry::Scalar(market_id))
+    }
+
+    // function for admins to close a scalar market with the value it came out at,
+    // same deadline as close(). the pool is split between LONG and SHORT, all to one
+    // side if nobody took the other
+    pub fn close_scalar(env: Env, market_id: u32, admin: Address, value: i128) -> Result<PredictionRecord, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        let mut config = Self::view_scalar(env.clone(), market_id).ok_or(Error::NotScalar)?;
+        if env.ledger().timestamp() <= pred.end_t + pred.lock_t {
+            return Err(Error::MarketRunning);
+        }
+
+        let pool = pred.total as i128 + pred.slashed;
+        let long = long_part(&config, value, pool).ok_or(Error::PayoutOverflow)?;
+        // the parts of the pool serve as the weights, so each side gets its part exactly
+        let weights = vec![&env, long, pool - long];
+        config.value = Some(value);
+        env.storage().persistent().set(&Registry::Scalar(market_id), &config);
+        Self::finish_split(&env, market_id, &admin, pred, &weights)
+    }
+}
@@ -0,0 +1,168 @@
+use soroban_sdk::{Address, BytesN, contractimpl, Env, IntoVal, Symbol, token, vec, Vec};
+
+use crate::{events, payout, Error, MarketStatus, PredictionRecord, Registry, VoteContract, VoteContractClient};
+
+// outcome-share tokens: a market can have one SEP-41 token per outcome, minted to
+// stakers 1:1 with their votes or LMSR shares. positions can then be transferred,
+// sold or used elsewhere, and whoever holds the tokens is who gets paid
+// claim() burns the caller's winning tokens for the payout and refund() burns every
+// outcome token they hold for their part of the pool. Record is still kept for each
+// staker but no longer decides payouts, since tokens may have changed hands
+// the tokens are deployed by this contract from the outcome token wasm set by the
+// contract admin (dapp/contracts/outcome_token), with this contract as their admin
+// so only it can mint and claw them back. a token passed in from outside could
+// report any balance, so none are accepted
+
+// salt for the share token of outcome in market_id, unique to each so no token
+// is ever shared between markets
+fn share_salt(env: &Env, market_id: u32, outcome: u32) -> BytesN<32> {
+    let mut salt = [0u8; 32];
+    salt[24..28].copy_from_slice(&market_id.to_be_bytes());
+    salt[28..].copy_from_slice(&outcome.to_be_bytes());
+    BytesN::from_array(env, &salt)
+}
+
+#[contractimpl]
+impl VoteContract {
+    // function for the contract admin to set the uploaded outcome token wasm that
+    // share tokens are deployed from, markets that already have tokens keep them
+    pub fn set_share_wasm(env: Env, admin: Address, wasm_hash: BytesN<32>) -> Result<(), Error> {
+        Self::check_contract_admin(&env, &admin)?;
+        env.storage().instance().set(&Registry::ShareWasm, &wasm_hash);
+
+        events::share_wasm(&env, &admin, &wasm_hash);
+        Ok(())
+    }
+
+    // function for admins to give a market outcome-share tokens, only before anything
+    // is staked. deploys one token per outcome, named after it and with the staking
+    // token's decimals, and returns their Addresses in the same order as the outcomes
+    pub fn set_share_tokens(env: Env, market_id: u32, admin: Address) -> Result<Vec<Address>, Error> {
+        Self::check_admin(&env, market_id, &admin)?;
+        let pred = Self::load_market(&env, market_id)?;
+        if pred.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if pred.total != 0 {
+            return Err(Error::MarketNotEmpty);
+        }
+        if Self::view_share_tokens(env.clone(), market_id).is_some() {
+            return Err(Error::SharesSet);
+        }
+        let wasm_hash: BytesN<32> = env.storage().instance().get(&Registry::ShareWasm).ok_or(Error::NoShareWasm)?;
+        let decimals = token::TokenClient::new(&env, &Self::view_token(env.clone(), market_id)?).decimals();
+
+        let mut tokens = Vec::new(&env);
+        for (i, outcome) in pred.outcomes.iter().enumerate() {
+            let salt = share_salt(&env, market_id, i as u32);
+            let token = env.deployer().with_current_contract(salt).deploy(wasm_hash.clone());
+            // OutcomeToken::init(admin, decimals, name, symbol)
+            let args = vec![
+                &env,
+                env.current_contract_address().into_val(&env),
+                decimals.into_val(&env),
+                outcome.into_val(&env),
+                outcome.into_val(&env),
+            ];
+            env.invoke_contract::<()>(&token, &Symbol::new(&env, "init"), args);
+            tokens.push_back(token);
+        }
+        env.storage().persistent().set(&Registry::ShareTokens(market_id), &tokens);
+        Self::extend_market(&env, market_id, &pred);
+
+        events::shares(&env, market_id, &admin, &tokens);
+        Ok(tokens)
+    }
+
+    // function to view the market's outcome-share tokens, if any
+    pub fn view_share_tokens(env: Env, market_id: u32) -> Option<Vec<Address>> {
+        env.storage().persistent().get(&Registry::ShareTokens(market_id))
+    }
+
+    // internal function to mint amount shares of outcome to user, if the market has
+    // share tokens
+    pub(crate) fn mint_shares(env: &Env, market_id: u32, user: &Address, outcome: u32, amount: u64) {
+        if let Some(token) = Self::view_share_tokens(env.clone(), market_id).and_then(|tokens| tokens.get(outcome)) {
+            token::StellarAssetClient::new(env, &token).mint(user, &(amount as i128));
+        }
+    }
+
+    // internal function to work out what the user's share tokens are worth once the
+    // market is resolved or cancelled
+    // returns the amount owed, the tokens of each outcome to burn for it and their sum
+    pub(crate) fn shares_owed(env: &Env, market_id: u32, poll: &PredictionRecord, tokens: &Vec<Address>, user: &Address) -> Result<(i128, Vec<i128>, u64), Error> {
+        let resolved = match poll.status {
+            MarketStatus::Resolved => true,
+            MarketStatus::Cancelled => false,
+            _ => return Err(Error::MarketNotResolved),
+        };
+        let split = Self::view_split(env.clone(), market_id);
+        // resolved markets only take the tokens that pay out, cancelled ones take them all
+        let mut burn = Vec::new(env);
+        let mut votes = Vec::new(env);
+        let mut held: u64 = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            let redeemed = match (resolved, poll.winner, &split) {
+                (false, _, _) => true,
+                (true, Some(winner), _) => winner == i as u32,
+                (true, None, Some(split)) => split.get(i as u32).unwrap_or(0) > 0,
+                (true, None, None) => false,
+            };
+            let balance = if redeemed { token::TokenClient::new(env, &token).balance(user) } else { 0 };
+            let balance_votes = u64::try_from(balance).map_err(|_| Error::PayoutOverflow)?;
+            held += balance_votes;
+            votes.push_back(balance_votes);
+            burn.push_back(balance);
+        }
+
+        if held == 0 {
+            return Err(if resolved { Error::NothingToClaim } else { Error::NoUserRecord });
+        }
+        let amount = match (resolved, poll.winner, &split) {
+            (false, _, _) => Self::share_refund(poll, held).ok_or(Error::PayoutOverflow)?,
+            (true, _, Some(split)) => Self::split_winnings(poll, &votes, split)?,
+            (true, Some(winner), None) => {
+                let lmsr = Self::view_lmsr(env.clone(), market_id).is_some();
+                Self::winnings(poll, winner, held, lmsr)?
+            }
+            (true, None, None) => return Err(Error::MarketNotResolved),
+        };
+        Ok((amount, burn, held))
+    }
+
+    // internal function to pay out share tokens, burns them and sends amount to user
+    pub(crate) fn redeem_shares(env: &Env, market_id: u32, user: &Address, mut poll: PredictionRecord, tokens: &Vec<Address>) -> Result<i128, Error> {
+        let (amount, burn, held) = Self::shares_owed(env, market_id, &poll, tokens, user)?;
+
+        poll.claimed_votes += held;
+        for (token, balance) in tokens.iter().zip(burn.iter()) {
+            if balance > 0 {
+                token::StellarAssetClient::new(env, &token).clawback(user, &balance);
+            }
+        }
+        Self::pay(env, market_id, &mut poll, user, amount)?;
+
+        if poll.status == MarketStatus::Cancelled {
+            events::refund(env, market_id, user, amount);
+        }
+        else {
+            events::claim(env, market_id, user, amount);
+        }
+        Ok(amount)
+    }
+
+    // internal function to work out the refund for held tokens of a cancelled market,
+    // its pool split pro rata over every outstanding token. that is 1:1 for stakes
+    // plus any exit fees kept, and what was paid on average for LMSR shares: a
+    // holder gets the average price of every share sold, not what they paid, since
+    // the tokens don't carry who bought them at what price
+    // the last refund takes the dust
+    fn share_refund(poll: &PredictionRecord, held: u64) -> Option<i128> {
+        let outstanding: u64 = poll.totals.iter().sum();
+        let pool = poll.total as i128 + poll.slashed;
+        if poll.claimed_votes + held == outstanding {
+            return payout::remainder(pool, poll.paid);
+        }
+        (held as i128).checked_mul(pool)?.checked_div(outstanding as i128)
+    }
+}
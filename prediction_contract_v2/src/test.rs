@@ -0,0 +1,1205 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{storage::{Instance as _, Persistent as _}, Address as _, Events, Ledger};
+use soroban_sdk::{token, vec, BytesN, Env, FromVal, IntoVal, String, Val};
+
+// a contract with one open market on a Stellar Asset Contract token, created by admin
+// with a duration of 100 seconds and outcomes "yes" and "no"
+struct Setup<'a> {
+    env: Env,
+    admin: Address,
+    contract: VoteContractClient<'a>,
+    token: token::Client<'a>,
+    token_admin: token::StellarAssetClient<'a>,
+}
+
+impl<'a> Setup<'a> {
+    fn new() -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let sac = env.register_stellar_asset_contract_v2(admin.clone());
+        let token = token::Client::new(&env, &sac.address());
+        let token_admin = token::StellarAssetClient::new(&env, &sac.address());
+
+        let contract_id = env.register_contract(None, VoteContract);
+        let contract = VoteContractClient::new(&env, &contract_id);
+        contract.init(&admin);
+        contract.create_market(&admin, &token.address, &100, &outcomes(&env), &None);
+
+        Setup { env, admin, contract, token, token_admin }
+    }
+
+    // a new address holding amount of the token
+    fn staker(&self, amount: i128) -> Address {
+        let user = Address::generate(&self.env);
+        self.token_admin.mint(&user, &amount);
+        user
+    }
+
+    fn pass(&self, seconds: u64) {
+        self.env.ledger().with_mut(|l| l.timestamp += seconds);
+    }
+
+    // outcome-share tokens deployed by the contract for market_id
+    fn share_tokens(&self, market_id: u32) -> Vec<Address> {
+        let wasm_hash = self.env.deployer().upload_contract_wasm(outcome_token::WASM);
+        self.contract.set_share_wasm(&self.admin, &wasm_hash);
+        self.contract.set_share_tokens(&market_id, &self.admin)
+    }
+}
+
+// built from dapp/contracts/outcome_token, see the README on rebuilding it
+mod outcome_token {
+    soroban_sdk::contractimport!(file = "fixtures/outcome_token.wasm");
+}
+
+// topics and data of the last event published
+fn last_event(env: &Env) -> (Vec<Val>, Val) {
+    let (_, topics, data) = env.events().all().last().unwrap();
+    (topics, data)
+}
+
+fn outcomes(env: &Env) -> Vec<String> {
+    vec![env, String::from_str(env, "yes"), String::from_str(env, "no")]
+}
+
+#[test]
+fn create_market() {
+    let s = Setup::new();
+
+    let pred = s.contract.view_predictions(&0);
+    assert_eq!(pred.status, MarketStatus::Open);
+    assert_eq!(pred.start_t, 0);
+    assert_eq!(pred.end_t, 100);
+    assert_eq!(pred.outcomes, outcomes(&s.env));
+    assert_eq!(pred.totals, vec![&s.env, 0, 0]);
+    assert_eq!(pred.winner, None);
+    assert_eq!(s.contract.view_token(&0), s.token.address);
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, s.admin.clone()]);
+    assert_eq!(s.contract.view_market_count(), 1);
+    assert_eq!(s.contract.view_version(), SCHEMA_VERSION);
+
+    let id = s.contract.create_market(&s.admin, &s.token.address, &50, &outcomes(&s.env), &None);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::CREATE, id, s.admin.clone()).into_val(&s.env));
+    let data = <(Address, u64, u64, Vec<String>)>::from_val(&s.env, &data);
+    assert_eq!(data, (s.token.address.clone(), 0, 50, outcomes(&s.env)));
+}
+
+#[test]
+fn create_market_needs_two_outcomes() {
+    let s = Setup::new();
+    let one = vec![&s.env, String::from_str(&s.env, "yes")];
+
+    let res = s.contract.try_create_market(&s.admin, &s.token.address, &100, &one, &None);
+    assert_eq!(res, Err(Ok(Error::NotEnoughOutcomes)));
+    assert_eq!(s.contract.view_market_count(), 1);
+}
+
+#[test]
+fn create_market_twice_opens_separate_markets() {
+    let s = Setup::new();
+    let other = Address::generate(&s.env);
+
+    let id = s.contract.create_market(&other, &s.token.address, &50, &outcomes(&s.env), &None);
+    assert_eq!(id, 1);
+    assert_eq!(s.contract.view_market_count(), 2);
+    assert_eq!(s.contract.view_predictions(&1).end_t, 50);
+    assert_eq!(s.contract.view_admins(&1), vec![&s.env, other.clone()]);
+    // the first market and its admins are left alone
+    assert_eq!(s.contract.view_predictions(&0).end_t, 100);
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, s.admin.clone()]);
+    assert_eq!(s.contract.try_lock(&1, &s.admin), Err(Ok(Error::AdminInvalid)));
+}
+
+#[test]
+fn init() {
+    let s = Setup::new();
+    let other = Address::generate(&s.env);
+
+    assert_eq!(s.contract.view_contract_admin(), Some(s.admin.clone()));
+    assert_eq!(s.contract.try_init(&other), Err(Ok(Error::AlreadyInit)));
+
+    // markets can't be opened on a contract nobody has claimed yet
+    let fresh = VoteContractClient::new(&s.env, &s.env.register_contract(None, VoteContract));
+    let res = fresh.try_create_market(&other, &s.token.address, &100, &outcomes(&s.env), &None);
+    assert_eq!(res, Err(Ok(Error::NotInit)));
+    assert_eq!(fresh.view_contract_admin(), None);
+
+    fresh.init(&other);
+    let (topics, _) = last_event(&s.env);
+    assert_eq!(topics, (events::INIT, other.clone()).into_val(&s.env));
+}
+
+#[test]
+fn upgrade_needs_contract_admin() {
+    let s = Setup::new();
+    let other = Address::generate(&s.env);
+    let hash = BytesN::from_array(&s.env, &[0; 32]);
+    s.contract.create_market(&other, &s.token.address, &100, &outcomes(&s.env), &None);
+    s.contract.add_admin(&0, &s.admin, &other);
+
+    // admins of market 0, or any other market, don't administer the contract
+    assert_eq!(s.contract.try_upgrade(&other, &hash), Err(Ok(Error::AdminInvalid)));
+    let fresh = VoteContractClient::new(&s.env, &s.env.register_contract(None, VoteContract));
+    assert_eq!(fresh.try_upgrade(&other, &hash), Err(Ok(Error::NotInit)));
+}
+
+#[test]
+fn migrate() {
+    let s = Setup::new();
+    let user = s.staker(10);
+    assert_eq!(s.contract.view_version(), SCHEMA_VERSION);
+
+    // storage written by this code is already current, so there is nothing to do
+    let published = s.env.events().all().len();
+    assert_eq!(s.contract.migrate(), SCHEMA_VERSION);
+    assert_eq!(s.env.events().all().len(), published);
+    assert_eq!(s.contract.view_version(), SCHEMA_VERSION);
+    s.contract.record_votes(&0, &user, &0, &10);
+}
+
+#[test]
+fn admin_transfer() {
+    let s = Setup::new();
+    let new_admin = Address::generate(&s.env);
+    let other = Address::generate(&s.env);
+
+    s.contract.propose_admin(&0, &s.admin, &new_admin);
+    assert_eq!(s.contract.view_pending_admin(&0), Some(AdminTransfer { from: s.admin.clone(), to: new_admin.clone() }));
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::ADMIN_PROPOSE, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(Address::from_val(&s.env, &data), new_admin);
+    // proposing changes nothing until the new admin accepts
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, s.admin.clone()]);
+    assert_eq!(s.contract.try_accept_admin(&0, &other), Err(Ok(Error::NoPendingAdmin)));
+
+    s.contract.accept_admin(&0, &new_admin);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::ADMIN_TRANSFER, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(Address::from_val(&s.env, &data), new_admin);
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, new_admin.clone()]);
+    assert_eq!(s.contract.view_pending_admin(&0), None);
+    assert_eq!(s.contract.try_lock(&0, &s.admin), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.try_accept_admin(&0, &new_admin), Err(Ok(Error::NoPendingAdmin)));
+    s.contract.lock(&0, &new_admin);
+}
+
+#[test]
+fn admin_transfer_lapses_when_proposer_removed() {
+    let s = Setup::new();
+    let new_admin = Address::generate(&s.env);
+    let other = Address::generate(&s.env);
+    s.contract.add_admin(&0, &s.admin, &other);
+
+    s.contract.propose_admin(&0, &s.admin, &new_admin);
+    s.contract.remove_admin(&0, &other, &s.admin);
+    assert_eq!(s.contract.try_accept_admin(&0, &new_admin), Err(Ok(Error::NoPendingAdmin)));
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, other.clone()]);
+    assert_eq!(s.contract.view_admin(&0, &new_admin).has_init, 0);
+}
+
+#[test]
+fn add_and_remove_admins() {
+    let s = Setup::new();
+    let other = Address::generate(&s.env);
+
+    s.contract.add_admin(&0, &s.admin, &other);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::ADMIN_ADD, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(Address::from_val(&s.env, &data), other);
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, s.admin.clone(), other.clone()]);
+    assert_eq!(s.contract.try_add_admin(&0, &other, &s.admin), Err(Ok(Error::AlreadyAdmin)));
+    let stranger = Address::generate(&s.env);
+    assert_eq!(s.contract.try_remove_admin(&0, &s.admin, &stranger), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.try_add_admin(&0, &stranger, &stranger), Err(Ok(Error::AdminInvalid)));
+
+    // admins can remove themselves, but never the last one
+    s.contract.remove_admin(&0, &s.admin, &s.admin);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::ADMIN_REMOVE, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(Address::from_val(&s.env, &data), s.admin);
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, other.clone()]);
+    assert_eq!(s.contract.try_remove_admin(&0, &other, &other), Err(Ok(Error::LastAdmin)));
+    assert_eq!(s.contract.view_admins(&0), vec![&s.env, other.clone()]);
+}
+
+#[test]
+fn unknown_market() {
+    let s = Setup::new();
+    let user = s.staker(10);
+
+    assert_eq!(s.contract.try_record_votes(&1, &user, &0, &10), Err(Ok(Error::NoMarket)));
+    assert_eq!(s.contract.try_view_token(&1), Err(Ok(Error::NoMarket)));
+    assert_eq!(s.contract.try_claim(&1, &user), Err(Ok(Error::NoMarket)));
+}
+
+#[test]
+fn record_votes() {
+    let s = Setup::new();
+    let user = s.staker(1000);
+
+    s.contract.record_votes(&0, &user, &0, &300);
+    s.contract.record_votes(&0, &user, &1, &100);
+    s.contract.record_votes(&0, &user, &0, &50);
+
+    let records = s.contract.view_voter(&0, &user);
+    assert_eq!(records.votes, vec![&s.env, 350, 100]);
+    assert_eq!(records.total, 450);
+    let pred = s.contract.view_predictions(&0);
+    assert_eq!(pred.totals, vec![&s.env, 350, 100]);
+    assert_eq!(pred.total, 450);
+    assert_eq!(s.token.balance(&user), 550);
+    assert_eq!(s.token.balance(&s.contract.address), 450);
+}
+
+#[test]
+fn record_votes_rejects_bad_stakes() {
+    let s = Setup::new();
+    let user = s.staker(100);
+
+    assert_eq!(s.contract.try_record_votes(&0, &user, &0, &0), Err(Ok(Error::InvalidVotes)));
+    assert_eq!(s.contract.try_record_votes(&0, &user, &2, &10), Err(Ok(Error::InvalidOutcome)));
+    // more than the user holds, the token transfer fails
+    assert!(s.contract.try_record_votes(&0, &user, &0, &101).is_err());
+    assert_eq!(s.token.balance(&user), 100);
+    assert_eq!(s.contract.view_predictions(&0).total, 0);
+}
+
+#[test]
+fn record_votes_after_deadline() {
+    let s = Setup::new();
+    let user = s.staker(100);
+
+    s.pass(100);
+    s.contract.record_votes(&0, &user, &0, &10);
+    s.pass(1);
+    assert_eq!(s.contract.try_record_votes(&0, &user, &0, &10), Err(Ok(Error::MarketEnded)));
+    assert_eq!(s.token.balance(&user), 90);
+}
+
+#[test]
+fn record_votes_after_lock() {
+    let s = Setup::new();
+    let user = s.staker(100);
+
+    s.contract.lock(&0, &s.admin);
+    assert_eq!(s.contract.try_record_votes(&0, &user, &0, &10), Err(Ok(Error::MarketNotOpen)));
+}
+
+#[test]
+fn close_before_deadline() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &0, &100);
+    s.contract.set_lock_period(&0, &s.admin, &50);
+
+    assert_eq!(s.contract.try_close(&0, &s.admin, &0), Err(Ok(Error::MarketRunning)));
+    s.pass(101);
+    // still inside the lock period
+    assert_eq!(s.contract.try_close(&0, &s.admin, &0), Err(Ok(Error::MarketRunning)));
+    s.pass(50);
+    assert_eq!(s.contract.close(&0, &s.admin, &0).status, MarketStatus::Resolved);
+}
+
+#[test]
+fn deadlines_past_the_last_timestamp() {
+    let s = Setup::new();
+    s.pass(10);
+
+    // end_t, the lock period and the claim window all have to fit
+    let res = s.contract.try_create_market(&s.admin, &s.token.address, &u64::MAX, &outcomes(&s.env), &None);
+    assert_eq!(res, Err(Ok(Error::InvalidPeriod)));
+    let longest = u64::MAX - CLAIM_WINDOW - 10;
+    let res = s.contract.try_create_market(&s.admin, &s.token.address, &(longest + 1), &outcomes(&s.env), &None);
+    assert_eq!(res, Err(Ok(Error::InvalidPeriod)));
+    assert_eq!(s.contract.view_market_count(), 1);
+    assert_eq!(s.contract.try_set_lock_period(&0, &s.admin, &u64::MAX), Err(Ok(Error::InvalidPeriod)));
+    assert_eq!(s.contract.try_set_lock_period(&0, &s.admin, &(u64::MAX - CLAIM_WINDOW - 99)), Err(Ok(Error::InvalidPeriod)));
+    assert_eq!(s.contract.view_predictions(&0).lock_t, 0);
+
+    // right up to the limit still works, and the market can't be closed early
+    let id = s.contract.create_market(&s.admin, &s.token.address, &longest, &outcomes(&s.env), &None);
+    assert_eq!(s.contract.view_predictions(&id).end_t, u64::MAX - CLAIM_WINDOW);
+    s.contract.set_lock_period(&0, &s.admin, &(u64::MAX - CLAIM_WINDOW - 100));
+    assert_eq!(s.contract.try_close(&0, &s.admin, &0), Err(Ok(Error::MarketRunning)));
+}
+
+#[test]
+fn close() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &1, &100);
+
+    s.pass(101);
+    let pred = s.contract.close(&0, &s.admin, &1);
+    assert_eq!(pred.status, MarketStatus::Resolved);
+    assert_eq!(pred.winner, Some(1));
+    assert_eq!(s.contract.view_predictions(&0), pred);
+    assert_eq!(s.contract.try_close(&0, &s.admin, &1), Err(Ok(Error::InvalidStatusChange)));
+}
+
+#[test]
+fn close_invalid_outcome() {
+    let s = Setup::new();
+
+    s.pass(101);
+    assert_eq!(s.contract.try_close(&0, &s.admin, &2), Err(Ok(Error::InvalidOutcome)));
+}
+
+#[test]
+fn close_by_non_admin() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &0, &100);
+
+    s.pass(101);
+    assert_eq!(s.contract.try_close(&0, &user, &0), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.try_lock(&0, &user), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.try_cancel(&0, &user), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.try_add_admin(&0, &user, &user), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.view_predictions(&0).status, MarketStatus::Open);
+}
+
+#[test]
+fn close_needs_admin_auth() {
+    let s = Setup::new();
+    s.pass(101);
+
+    // drop the mocked auths, nothing is authorized from here on
+    s.env.set_auths(&[]);
+    assert!(s.contract.try_close(&0, &s.admin, &0).is_err());
+    assert_eq!(s.contract.view_predictions(&0).status, MarketStatus::Open);
+}
+
+#[test]
+fn claim() {
+    let s = Setup::new();
+    let a = s.staker(1000);
+    let b = s.staker(1000);
+    let c = s.staker(1000);
+    s.contract.record_votes(&0, &a, &0, &300);
+    s.contract.record_votes(&0, &b, &0, &100);
+    s.contract.record_votes(&0, &c, &1, &200);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.view_claimable(&0, &a), 450);
+    assert_eq!(s.contract.view_claimable(&0, &c), 0);
+
+    // 600 staked, split 3:1 between the winners
+    assert_eq!(s.contract.claim(&0, &a), 450);
+    assert_eq!(s.contract.claim(&0, &b), 150);
+    assert_eq!(s.contract.try_claim(&0, &c), Err(Ok(Error::NothingToClaim)));
+    assert_eq!(s.token.balance(&a), 1150);
+    assert_eq!(s.token.balance(&b), 1050);
+    assert_eq!(s.token.balance(&c), 800);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+    assert_eq!(s.contract.view_predictions(&0).paid, 600);
+}
+
+#[test]
+fn claim_rounding_dust() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    let c = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &1);
+    s.contract.record_votes(&0, &b, &0, &2);
+    s.contract.record_votes(&0, &c, &1, &8);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    // 11 * 1 / 3 rounds down, the last claimer takes what is left
+    assert_eq!(s.contract.claim(&0, &a), 3);
+    assert_eq!(s.contract.claim(&0, &b), 8);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn claim_twice() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &0, &100);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.claim(&0, &user), 100);
+    assert_eq!(s.contract.try_claim(&0, &user), Err(Ok(Error::AlreadyClaimed)));
+    assert_eq!(s.contract.view_claimable(&0, &user), 0);
+    assert_eq!(s.token.balance(&user), 100);
+}
+
+#[test]
+fn claim_before_close() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    let nobody = s.staker(0);
+    s.contract.record_votes(&0, &user, &0, &100);
+
+    assert_eq!(s.contract.try_claim(&0, &user), Err(Ok(Error::MarketNotResolved)));
+    assert_eq!(s.contract.try_claim(&0, &nobody), Err(Ok(Error::NoUserRecord)));
+    s.pass(101);
+    assert_eq!(s.contract.try_claim(&0, &user), Err(Ok(Error::MarketNotResolved)));
+    assert_eq!(s.token.balance(&s.contract.address), 100);
+}
+
+#[test]
+fn claim_needs_user_auth() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &0, &100);
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+
+    // drop the mocked auths, nothing is authorized from here on
+    s.env.set_auths(&[]);
+    assert!(s.contract.try_claim(&0, &user).is_err());
+    assert_eq!(s.token.balance(&s.contract.address), 100);
+}
+
+#[test]
+fn claim_only_pays_from_own_market() {
+    let s = Setup::new();
+    let a = s.staker(1000);
+    let b = s.staker(1000);
+    s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &None);
+    s.contract.record_votes(&0, &a, &0, &100);
+    s.contract.record_votes(&0, &b, &1, &100);
+    s.contract.record_votes(&1, &a, &1, &300);
+    s.contract.record_votes(&1, &b, &0, &500);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.claim(&0, &a), 200);
+    assert_eq!(s.contract.try_claim(&1, &a), Err(Ok(Error::MarketNotResolved)));
+    assert_eq!(s.token.balance(&s.contract.address), 800);
+
+    s.contract.close(&1, &s.admin, &0);
+    assert_eq!(s.contract.claim(&1, &b), 800);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn cancel_and_refund() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &40);
+    s.contract.record_votes(&0, &a, &1, &20);
+    s.contract.record_votes(&0, &b, &1, &30);
+
+    assert_eq!(s.contract.try_refund(&0, &a), Err(Ok(Error::MarketNotCancelled)));
+    s.contract.cancel(&0, &s.admin);
+    assert_eq!(s.contract.try_claim(&0, &a), Err(Ok(Error::MarketNotResolved)));
+    assert_eq!(s.contract.view_claimable(&0, &a), 60);
+    assert_eq!(s.contract.refund(&0, &a), 60);
+    assert_eq!(s.contract.refund(&0, &b), 30);
+    assert_eq!(s.contract.try_refund(&0, &a), Err(Ok(Error::AlreadyClaimed)));
+    assert_eq!(s.token.balance(&a), 100);
+    assert_eq!(s.token.balance(&b), 100);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn close_with_unbacked_winner_cancels() {
+    let s = Setup::new();
+    let user = s.staker(100);
+    s.contract.record_votes(&0, &user, &0, &100);
+
+    s.pass(101);
+    let pred = s.contract.close(&0, &s.admin, &1);
+    assert_eq!(pred.status, MarketStatus::Cancelled);
+    assert_eq!(pred.winner, None);
+    assert_eq!(s.contract.refund(&0, &user), 100);
+    assert_eq!(s.token.balance(&user), 100);
+}
+
+#[test]
+fn oracle_market() {
+    let s = Setup::new();
+    let oracle = mock_oracle::MockOracleClient::new(&s.env, &s.env.register_contract(None, mock_oracle::MockOracle));
+    let config = OracleConfig { oracle: oracle.address.clone(), market_id: 7 };
+    s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &Some(config));
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.record_votes(&1, &a, &0, &10);
+    s.contract.record_votes(&1, &b, &1, &30);
+    assert_eq!(s.contract.view_oracle(&1).unwrap().market_id, 7);
+
+    assert_eq!(s.contract.try_resolve(&1), Err(Ok(Error::MarketRunning)));
+    s.pass(101);
+    // the admins can't pick the winner of an oracle market
+    assert_eq!(s.contract.try_close(&1, &s.admin, &0), Err(Ok(Error::OracleMarket)));
+    let weights = vec![&s.env, WEIGHT_TOTAL / 2, WEIGHT_TOTAL / 2];
+    assert_eq!(s.contract.try_close_split(&1, &s.admin, &weights), Err(Ok(Error::OracleMarket)));
+    assert_eq!(s.contract.try_resolve(&1), Err(Ok(Error::OracleNoOutcome)));
+    oracle.set_outcome(&7, &2);
+    assert_eq!(s.contract.try_resolve(&1), Err(Ok(Error::InvalidOutcome)));
+    assert_eq!(s.contract.view_predictions(&1).status, MarketStatus::Open);
+
+    oracle.set_outcome(&7, &0);
+    let pred = s.contract.resolve(&1);
+    assert_eq!(pred.status, MarketStatus::Resolved);
+    assert_eq!(pred.winner, Some(0));
+    assert_eq!(s.contract.try_resolve(&1), Err(Ok(Error::InvalidStatusChange)));
+    assert_eq!(s.contract.claim(&1, &a), 40);
+}
+
+#[test]
+fn resolve_without_oracle() {
+    let s = Setup::new();
+    s.pass(101);
+
+    assert_eq!(s.contract.try_resolve(&0), Err(Ok(Error::NoOracle)));
+    assert!(s.contract.view_oracle(&0).is_none());
+}
+
+// gives market 0 a 50 second dispute window with a bond of 5, stakes 10 on each
+// outcome and proposes "yes" once the market has ended. returns the yes and no stakers
+fn proposed(s: &Setup, arbiter: &Address) -> (Address, Address) {
+    let config = DisputeConfig { arbiter: arbiter.clone(), window: 50, bond: 5 };
+    s.contract.set_dispute(&0, &s.admin, &config);
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &10);
+    s.contract.record_votes(&0, &b, &1, &10);
+    s.pass(101);
+    assert_eq!(s.contract.close(&0, &s.admin, &0).status, MarketStatus::Proposed);
+    (a, b)
+}
+
+#[test]
+fn setters_publish_events() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+
+    s.contract.set_lock_period(&0, &s.admin, &60);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::LOCK_PERIOD, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(u64::from_val(&s.env, &data), 60);
+
+    s.contract.set_exit_fee(&0, &s.admin, &250);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::EXIT_FEE, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(u32::from_val(&s.env, &data), 250);
+
+    s.contract.set_dispute(&0, &s.admin, &DisputeConfig { arbiter: arbiter.clone(), window: 50, bond: 5 });
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::DISPUTE, 0u32, s.admin.clone()).into_val(&s.env));
+    assert_eq!(<(Address, u64, i128)>::from_val(&s.env, &data), (arbiter, 50, 5));
+
+    let wasm_hash = s.env.deployer().upload_contract_wasm(outcome_token::WASM);
+    s.contract.set_share_wasm(&s.admin, &wasm_hash);
+    let (topics, data) = last_event(&s.env);
+    assert_eq!(topics, (events::SHARE_WASM, s.admin.clone()).into_val(&s.env));
+    assert_eq!(BytesN::<32>::from_val(&s.env, &data), wasm_hash);
+}
+
+#[test]
+fn set_dispute_checks() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+    let config = |window, bond| DisputeConfig { arbiter: arbiter.clone(), window, bond };
+
+    assert_eq!(s.contract.try_set_dispute(&0, &s.admin, &config(50, -1)), Err(Ok(Error::InvalidBond)));
+    assert_eq!(s.contract.try_set_dispute(&0, &s.admin, &config(50, 0)), Err(Ok(Error::InvalidBond)));
+    assert_eq!(s.contract.try_set_dispute(&0, &s.admin, &config(0, 5)), Err(Ok(Error::InvalidWindow)));
+    // a window this long would overflow proposed_t + window
+    assert_eq!(s.contract.try_set_dispute(&0, &s.admin, &config(u64::MAX, 5)), Err(Ok(Error::InvalidWindow)));
+    s.contract.set_dispute(&0, &s.admin, &config(30 * 24 * 60 * 60, 5));
+    assert_eq!(s.contract.view_dispute(&0).unwrap().window, 30 * 24 * 60 * 60);
+    let res = s.contract.try_set_dispute(&0, &Address::generate(&s.env), &config(50, 5));
+    assert_eq!(res, Err(Ok(Error::AdminInvalid)));
+}
+
+#[test]
+fn dispute_window_edges() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+    let (a, b) = proposed(&s, &arbiter);
+    assert_eq!(s.contract.try_claim(&0, &a), Err(Ok(Error::MarketNotResolved)));
+
+    // the window runs up to and including proposed_t + window
+    s.pass(50);
+    assert_eq!(s.contract.try_finalize(&0), Err(Ok(Error::DisputeWindowOpen)));
+    assert_eq!(s.contract.try_challenge(&0, &b, &0), Err(Ok(Error::SameOutcome)));
+    s.pass(1);
+    assert_eq!(s.contract.try_challenge(&0, &b, &1), Err(Ok(Error::DisputeWindowClosed)));
+    assert_eq!(s.contract.finalize(&0).status, MarketStatus::Resolved);
+    // nobody resolved it by hand, so the contract itself is in the caller's place
+    let (topics, _) = last_event(&s.env);
+    assert_eq!(topics, (events::RESOLVE, 0u32, s.contract.address.clone()).into_val(&s.env));
+    assert_eq!(s.contract.try_finalize(&0), Err(Ok(Error::MarketNotProposed)));
+    assert_eq!(s.contract.claim(&0, &a), 20);
+}
+
+#[test]
+fn dispute_upheld_slashes_bond() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+    let (a, b) = proposed(&s, &arbiter);
+    let outsider = s.staker(100);
+
+    assert_eq!(s.contract.try_challenge(&0, &outsider, &1), Err(Ok(Error::NoUserRecord)));
+    assert_eq!(s.contract.challenge(&0, &b, &1).status, MarketStatus::Disputed);
+    assert_eq!(s.token.balance(&b), 85);
+    assert_eq!(s.contract.view_challenge(&0).unwrap().outcome, 1);
+    // only the first challenge is taken, and the admins can't walk away from it
+    assert_eq!(s.contract.try_challenge(&0, &a, &1), Err(Ok(Error::MarketNotProposed)));
+    assert_eq!(s.contract.try_cancel(&0, &s.admin), Err(Ok(Error::MarketDisputed)));
+    assert_eq!(s.contract.try_finalize(&0), Err(Ok(Error::MarketNotProposed)));
+    assert_eq!(s.contract.try_arbitrate(&0, &s.admin, &0), Err(Ok(Error::NotArbiter)));
+
+    let pred = s.contract.arbitrate(&0, &arbiter, &0);
+    assert_eq!(pred.status, MarketStatus::Resolved);
+    assert_eq!(pred.slashed, 5);
+    assert!(s.contract.view_challenge(&0).is_none());
+    assert_eq!(s.contract.claim(&0, &a), 25);
+    assert_eq!(s.token.balance(&b), 85);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn dispute_overturned_returns_bond() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+    let (a, b) = proposed(&s, &arbiter);
+    s.contract.challenge(&0, &b, &1);
+
+    let pred = s.contract.arbitrate(&0, &arbiter, &1);
+    assert_eq!(pred.winner, Some(1));
+    assert_eq!(pred.slashed, 0);
+    assert_eq!(s.token.balance(&b), 90);
+    assert_eq!(s.contract.try_claim(&0, &a), Err(Ok(Error::NothingToClaim)));
+    assert_eq!(s.contract.claim(&0, &b), 20);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn expire_dispute() {
+    let s = Setup::new();
+    let arbiter = Address::generate(&s.env);
+    let (a, b) = proposed(&s, &arbiter);
+    assert_eq!(s.contract.try_expire_dispute(&0), Err(Ok(Error::MarketNotDisputed)));
+    s.contract.challenge(&0, &b, &1);
+
+    s.pass(ARBITRATION_TIMEOUT);
+    assert_eq!(s.contract.try_expire_dispute(&0), Err(Ok(Error::ArbitrationPending)));
+    s.pass(1);
+    assert_eq!(s.contract.expire_dispute(&0).status, MarketStatus::Cancelled);
+    let (topics, _) = last_event(&s.env);
+    assert_eq!(topics, (events::CANCEL, 0u32, s.contract.address.clone()).into_val(&s.env));
+    assert_eq!(s.token.balance(&b), 90);
+    assert_eq!(s.contract.try_arbitrate(&0, &arbiter, &0), Err(Ok(Error::MarketNotDisputed)));
+    assert_eq!(s.contract.refund(&0, &a), 10);
+    assert_eq!(s.contract.refund(&0, &b), 10);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn bump_extends_market_and_records() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &10);
+    s.contract.record_votes(&0, &b, &1, &10);
+    let ttl = |key: &Registry| s.env.as_contract(&s.contract.address, || s.env.storage().persistent().get_ttl(key));
+    let instance_ttl = || s.env.as_contract(&s.contract.address, || s.env.storage().instance().get_ttl());
+    let record = Registry::Record(0, a.clone());
+
+    // everything lives until the claim window after end_t has passed
+    let until_claimed = ((100 + 30 * 24 * 60 * 60) / 5) as u32;
+    assert_eq!(ttl(&Registry::Prediction(0)), until_claimed);
+    assert_eq!(ttl(&record), until_claimed);
+    assert!(instance_ttl() >= until_claimed);
+
+    // a long way on, bump() gives the market and the voters passed a full claim window again
+    s.env.ledger().with_mut(|l| {
+        l.sequence_number += 400_000;
+        l.timestamp += 400_000 * 5;
+    });
+    assert_eq!(ttl(&record), until_claimed - 400_000);
+    s.contract.bump(&0, &vec![&s.env, a.clone(), Address::generate(&s.env)]);
+    let window = (30 * 24 * 60 * 60 / 5) as u32;
+    assert_eq!(ttl(&Registry::Prediction(0)), window);
+    assert_eq!(ttl(&Registry::Token(0)), window);
+    assert_eq!(ttl(&Registry::Admin(0, s.admin.clone())), window);
+    assert_eq!(ttl(&record), window);
+    // voters not passed in are left as they were
+    assert_eq!(ttl(&Registry::Record(0, b.clone())), until_claimed - 400_000);
+    assert!(instance_ttl() >= window);
+
+    assert_eq!(s.contract.try_bump(&1, &vec![&s.env, a.clone()]), Err(Ok(Error::NoMarket)));
+    s.pass(1);
+    assert_eq!(s.contract.close(&0, &s.admin, &0).status, MarketStatus::Resolved);
+    assert_eq!(s.contract.view_claimable(&0, &a), 20);
+}
+
+#[test]
+fn fixed_point() {
+    use crate::fixed::{exp_neg, ln, LN_2, SCALE};
+
+    assert_eq!(exp_neg(0), SCALE);
+    assert!((exp_neg(-SCALE) - 367_879_441_171_442_321).abs() < 100);
+    assert_eq!(exp_neg(-50 * SCALE), 0);
+    assert_eq!(ln(SCALE), 0);
+    assert!((ln(2 * SCALE) - LN_2).abs() < 100);
+    assert!((ln(exp_neg(-3 * SCALE)) + 3 * SCALE).abs() < 1_000_000_000);
+}
+
+#[test]
+fn lmsr_buy_and_redeem() {
+    let s = Setup::new();
+    s.token_admin.mint(&s.admin, &1000);
+    let a = s.staker(1000);
+    let b = s.staker(1000);
+
+    // worst case loss is 1000 * ln(2), rounded up with a unit to spare
+    let config = s.contract.set_lmsr(&0, &s.admin, &1000);
+    assert_eq!(config.subsidy, 695);
+    assert_eq!(s.token.balance(&s.admin), 305);
+    assert_eq!(s.contract.view_prices(&0), vec![&s.env, fixed::SCALE / 2, fixed::SCALE / 2]);
+    assert_eq!(s.contract.try_record_votes(&0, &a, &0, &10), Err(Ok(Error::LmsrMarket)));
+
+    // 1000 * ln((e^0.1 + 1) / 2) = 51.25
+    assert_eq!(s.contract.view_quote(&0, &0, &100), 52);
+    assert_eq!(s.contract.try_buy_shares(&0, &a, &0, &100, &51), Err(Ok(Error::CostTooHigh)));
+    assert_eq!(s.contract.buy_shares(&0, &a, &0, &100, &52), 52);
+    assert!(s.contract.view_prices(&0).get(0).unwrap() > fixed::SCALE / 2);
+    // the other side is cheaper now, 48.75
+    assert_eq!(s.contract.buy_shares(&0, &b, &1, &100, &100), 49);
+    assert_eq!(s.contract.view_voter(&0, &a).votes, vec![&s.env, 100, 0]);
+    assert_eq!(s.contract.view_predictions(&0).totals, vec![&s.env, 100, 100]);
+    assert_eq!(s.token.balance(&s.contract.address), 695 + 52 + 49);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.try_reclaim_subsidy(&0, &a), Err(Ok(Error::AdminInvalid)));
+    // winning shares redeem 1:1
+    assert_eq!(s.contract.claim(&0, &a), 100);
+    assert_eq!(s.contract.try_claim(&0, &b), Err(Ok(Error::NothingToClaim)));
+    assert_eq!(s.contract.reclaim_subsidy(&0, &s.admin), 695 + 52 + 49 - 100);
+    assert_eq!(s.contract.try_reclaim_subsidy(&0, &s.admin), Err(Ok(Error::AlreadyClaimed)));
+    assert_eq!(s.token.balance(&a), 1048);
+    assert_eq!(s.token.balance(&b), 951);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn lmsr_cancel() {
+    let s = Setup::new();
+    s.token_admin.mint(&s.admin, &1000);
+    let a = s.staker(1000);
+    s.contract.set_lmsr(&0, &s.admin, &1000);
+    let paid = s.contract.buy_shares(&0, &a, &1, &300, &1000);
+
+    assert_eq!(s.contract.try_reclaim_subsidy(&0, &s.admin), Err(Ok(Error::MarketNotResolved)));
+    s.contract.cancel(&0, &s.admin);
+    // the refund is what was paid, not the shares
+    assert_eq!(s.contract.refund(&0, &a), paid);
+    assert_eq!(s.contract.reclaim_subsidy(&0, &s.admin), 695);
+    assert_eq!(s.token.balance(&a), 1000);
+    assert_eq!(s.token.balance(&s.admin), 1000);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn set_lmsr_checks() {
+    let s = Setup::new();
+    s.token_admin.mint(&s.admin, &1000);
+    let a = s.staker(100);
+
+    assert_eq!(s.contract.try_buy_shares(&0, &a, &0, &10, &100), Err(Ok(Error::NotLmsr)));
+    assert_eq!(s.contract.try_set_lmsr(&0, &s.admin, &0), Err(Ok(Error::InvalidLiquidity)));
+    assert_eq!(s.contract.try_set_lmsr(&0, &a, &1000), Err(Ok(Error::AdminInvalid)));
+    s.contract.record_votes(&0, &a, &0, &10);
+    assert_eq!(s.contract.try_set_lmsr(&0, &s.admin, &1000), Err(Ok(Error::MarketNotEmpty)));
+    assert_eq!(s.token.balance(&s.admin), 1000);
+}
+
+#[test]
+fn share_tokens_move_positions() {
+    let s = Setup::new();
+    let tokens = s.share_tokens(0);
+    let yes = token::Client::new(&s.env, &tokens.get(0).unwrap());
+    let no = token::Client::new(&s.env, &tokens.get(1).unwrap());
+    let a = s.staker(100);
+    let b = s.staker(100);
+    let c = Address::generate(&s.env);
+
+    s.contract.record_votes(&0, &a, &0, &30);
+    s.contract.record_votes(&0, &b, &1, &10);
+    assert_eq!(yes.balance(&a), 30);
+    assert_eq!(no.balance(&b), 10);
+
+    // a sells a third of their position to c, who can then claim it
+    yes.transfer(&a, &c, &10);
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.view_claimable(&0, &c), 13);
+    assert_eq!(s.contract.claim(&0, &c), 13);
+    assert_eq!(s.contract.try_claim(&0, &c), Err(Ok(Error::NothingToClaim)));
+    assert_eq!(s.contract.try_claim(&0, &b), Err(Ok(Error::NothingToClaim)));
+    // the last winning tokens take the rounding dust
+    assert_eq!(s.contract.claim(&0, &a), 27);
+    assert_eq!(yes.balance(&a), 0);
+    assert_eq!(yes.balance(&c), 0);
+    assert_eq!(s.token.balance(&c), 13);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn share_tokens_refund() {
+    let s = Setup::new();
+    let tokens = s.share_tokens(0);
+    let yes = token::Client::new(&s.env, &tokens.get(0).unwrap());
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &30);
+    s.contract.record_votes(&0, &a, &1, &20);
+    s.contract.record_votes(&0, &b, &0, &10);
+
+    yes.transfer(&a, &b, &5);
+    assert_eq!(s.contract.try_refund(&0, &a), Err(Ok(Error::MarketNotCancelled)));
+    s.contract.cancel(&0, &s.admin);
+    // whoever holds the tokens gets the refund, one unit per token on any outcome
+    assert_eq!(s.contract.refund(&0, &a), 45);
+    assert_eq!(s.contract.refund(&0, &b), 15);
+    assert_eq!(s.contract.try_refund(&0, &a), Err(Ok(Error::NoUserRecord)));
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+// a share token that says everyone holds one share and ignores mints and burns
+mod hostile {
+    use soroban_sdk::{contract, contractimpl, Address, Env};
+
+    #[contract]
+    pub struct HostileToken;
+
+    #[contractimpl]
+    impl HostileToken {
+        pub fn balance(_env: Env, _id: Address) -> i128 {
+            1
+        }
+
+        pub fn mint(_env: Env, _to: Address, _amount: i128) {}
+
+        pub fn clawback(_env: Env, _from: Address, _amount: i128) {}
+    }
+}
+
+#[test]
+fn hostile_share_tokens_cant_overdraw() {
+    let s = Setup::new();
+    let a = s.staker(10);
+    let b = s.staker(10);
+    let c = s.staker(100);
+    let attacker = Address::generate(&s.env);
+    s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &None);
+    let tokens = vec![&s.env, s.env.register_contract(None, hostile::HostileToken), s.env.register_contract(None, hostile::HostileToken)];
+    // set_share_tokens() only deploys tokens of its own, so this stands in for a
+    // market that somehow ended up with tokens that lie about balances
+    s.env.as_contract(&s.contract.address, || s.env.storage().persistent().set(&Registry::ShareTokens(1), &tokens));
+    s.contract.record_votes(&1, &a, &0, &10);
+    s.contract.record_votes(&1, &b, &1, &10);
+    // market 0 holds funds of its own in the same contract
+    s.contract.record_votes(&0, &c, &0, &100);
+    s.pass(101);
+    s.contract.close(&1, &s.admin, &0);
+
+    // each claim burns nothing, but the market never pays out more than its pool
+    let mut paid = 0;
+    while let Ok(Ok(amount)) = s.contract.try_claim(&1, &attacker) {
+        paid += amount;
+    }
+    assert_eq!(paid, 20);
+    assert_eq!(s.contract.try_claim(&1, &attacker), Err(Ok(Error::PayoutOverflow)));
+    assert_eq!(s.contract.view_predictions(&1).paid, 20);
+    assert_eq!(s.token.balance(&s.contract.address), 100);
+}
+
+#[test]
+fn set_share_tokens_checks() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let other = Address::generate(&s.env);
+    s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &None);
+    assert_eq!(s.contract.try_set_share_tokens(&1, &s.admin), Err(Ok(Error::NoShareWasm)));
+    let wasm_hash = s.env.deployer().upload_contract_wasm(outcome_token::WASM);
+    // only the contract admin picks the code share tokens run
+    s.contract.add_admin(&0, &s.admin, &other);
+    assert_eq!(s.contract.try_set_share_wasm(&other, &wasm_hash), Err(Ok(Error::AdminInvalid)));
+    let tokens = s.share_tokens(0);
+
+    assert_eq!(s.contract.view_share_tokens(&0), Some(tokens.clone()));
+    assert_eq!(s.contract.try_set_share_tokens(&0, &s.admin), Err(Ok(Error::SharesSet)));
+    let yes = outcome_token::Client::new(&s.env, &tokens.get(0).unwrap());
+    assert_eq!(yes.name(), String::from_str(&s.env, "yes"));
+    assert_eq!(yes.decimals(), s.token.decimals());
+    // every market gets tokens of its own
+    let other_tokens = s.contract.set_share_tokens(&1, &s.admin);
+    assert!(!other_tokens.contains(tokens.get(0).unwrap()) && !other_tokens.contains(tokens.get(1).unwrap()));
+    s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &None);
+    s.contract.record_votes(&2, &a, &0, &10);
+    assert_eq!(s.contract.try_set_share_tokens(&2, &s.admin), Err(Ok(Error::MarketNotEmpty)));
+    assert_eq!(s.contract.view_share_tokens(&2), None);
+}
+
+#[test]
+fn exit_position() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.set_exit_fee(&0, &s.admin, &500);
+    s.contract.record_votes(&0, &a, &0, &100);
+    s.contract.record_votes(&0, &b, &1, &50);
+
+    // 5% of 40 stays in the pool
+    assert_eq!(s.contract.exit_position(&0, &a, &0, &40), 38);
+    let records = s.contract.view_voter(&0, &a);
+    assert_eq!(records.votes, vec![&s.env, 60, 0]);
+    assert_eq!(records.total, 60);
+    let pred = s.contract.view_predictions(&0);
+    assert_eq!(pred.totals, vec![&s.env, 60, 50]);
+    assert_eq!(pred.total, 110);
+    assert_eq!(pred.slashed, 2);
+
+    s.pass(101);
+    s.contract.close(&0, &s.admin, &0);
+    assert_eq!(s.contract.claim(&0, &a), 112);
+    assert_eq!(s.token.balance(&a), 150);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn exit_position_checks() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &50);
+
+    assert_eq!(s.contract.try_set_exit_fee(&0, &s.admin, &10_001), Err(Ok(Error::InvalidExitFee)));
+    assert_eq!(s.contract.try_set_exit_fee(&0, &a, &100), Err(Ok(Error::AdminInvalid)));
+    assert_eq!(s.contract.view_exit_fee(&0), 0);
+    assert_eq!(s.contract.try_exit_position(&0, &a, &0, &0), Err(Ok(Error::InvalidVotes)));
+    assert_eq!(s.contract.try_exit_position(&0, &a, &0, &51), Err(Ok(Error::NotEnoughVotes)));
+    assert_eq!(s.contract.try_exit_position(&0, &a, &1, &1), Err(Ok(Error::NotEnoughVotes)));
+    assert_eq!(s.contract.try_exit_position(&0, &a, &2, &1), Err(Ok(Error::InvalidOutcome)));
+    // without a fee the whole stake comes back
+    assert_eq!(s.contract.exit_position(&0, &a, &0, &10), 10);
+
+    s.pass(101);
+    assert_eq!(s.contract.try_exit_position(&0, &a, &0, &10), Err(Ok(Error::MarketEnded)));
+    s.contract.lock(&0, &s.admin);
+    assert_eq!(s.contract.try_exit_position(&0, &a, &0, &10), Err(Ok(Error::MarketNotOpen)));
+    assert_eq!(s.token.balance(&a), 60);
+}
+
+#[test]
+fn exit_fees_are_refunded_pro_rata() {
+    let s = Setup::new();
+    let tokens = s.share_tokens(0);
+    let yes = token::Client::new(&s.env, &tokens.get(0).unwrap());
+    let a = s.staker(100);
+    let b = s.staker(100);
+    s.contract.set_exit_fee(&0, &s.admin, &1000);
+    s.contract.record_votes(&0, &a, &0, &100);
+    s.contract.record_votes(&0, &b, &1, &100);
+
+    // exiting burns the share tokens
+    assert_eq!(s.contract.exit_position(&0, &a, &0, &50), 45);
+    assert_eq!(yes.balance(&a), 50);
+    s.contract.cancel(&0, &s.admin);
+    // the 5 kept is split over the 150 votes left
+    assert_eq!(s.contract.refund(&0, &a), 51);
+    assert_eq!(s.contract.refund(&0, &b), 104);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn scalar_market() {
+    let s = Setup::new();
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &0, &1000);
+    let a = s.staker(100);
+    let b = s.staker(100);
+    let c = s.staker(100);
+    s.contract.record_votes(&id, &a, &LONG, &60);
+    s.contract.record_votes(&id, &b, &SHORT, &40);
+    s.contract.record_votes(&id, &c, &LONG, &20);
+
+    s.pass(101);
+    assert_eq!(s.contract.try_close(&id, &s.admin, &LONG), Err(Ok(Error::ScalarMarket)));
+    s.contract.close_scalar(&id, &s.admin, &750);
+    assert_eq!(s.contract.view_scalar(&id).unwrap().value, Some(750));
+    // three quarters of the way up the range, so long gets 3/4 of the pool
+    assert_eq!(s.contract.view_split(&id), Some(vec![&s.env, 90, 30]));
+    assert_eq!(s.contract.view_predictions(&id).status, MarketStatus::Resolved);
+
+    assert_eq!(s.contract.claim(&id, &a), 67);
+    assert_eq!(s.contract.claim(&id, &b), 30);
+    // the last claim takes the rounding dust
+    assert_eq!(s.contract.claim(&id, &c), 23);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+
+    // a third of the way up, long gets exactly a third and not 3333 basis points of it
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &0, &3);
+    s.contract.record_votes(&id, &a, &LONG, &10);
+    s.contract.record_votes(&id, &b, &SHORT, &20);
+    s.pass(101);
+    s.contract.close_scalar(&id, &s.admin, &1);
+    assert_eq!(s.contract.view_split(&id), Some(vec![&s.env, 10, 20]));
+    assert_eq!(s.contract.claim(&id, &a), 10);
+}
+
+#[test]
+fn scalar_market_wide_range() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &i128::MIN, &i128::MAX);
+    s.contract.record_votes(&id, &a, &LONG, &30);
+    s.contract.record_votes(&id, &b, &SHORT, &10);
+    s.pass(101);
+
+    // the middle of the widest range splits the pool evenly
+    s.contract.close_scalar(&id, &s.admin, &0);
+    assert_eq!(s.contract.view_split(&id), Some(vec![&s.env, 20, 20]));
+    assert_eq!(s.contract.claim(&id, &a), 20);
+    assert_eq!(s.contract.claim(&id, &b), 20);
+
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &i128::MIN, &i128::MAX);
+    s.contract.record_votes(&id, &a, &LONG, &30);
+    s.contract.record_votes(&id, &b, &SHORT, &10);
+    s.pass(101);
+    s.contract.close_scalar(&id, &s.admin, &i128::MAX);
+    assert_eq!(s.contract.view_split(&id), Some(vec![&s.env, 40, 0]));
+}
+
+#[test]
+fn scalar_market_bounds() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    assert_eq!(s.contract.try_create_scalar_market(&s.admin, &s.token.address, &100, &5, &5), Err(Ok(Error::InvalidBounds)));
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &-50, &50);
+    assert_eq!(s.contract.try_set_lmsr(&id, &s.admin, &1000), Err(Ok(Error::ScalarMarket)));
+    s.contract.record_votes(&id, &a, &LONG, &30);
+    s.contract.record_votes(&id, &b, &SHORT, &10);
+
+    assert_eq!(s.contract.try_close_scalar(&0, &s.admin, &10), Err(Ok(Error::NotScalar)));
+    assert_eq!(s.contract.try_close_scalar(&id, &s.admin, &10), Err(Ok(Error::MarketRunning)));
+    s.pass(101);
+    // past the upper bound counts as the bound, the short side gets nothing
+    s.contract.close_scalar(&id, &s.admin, &80);
+    assert_eq!(s.contract.view_split(&id), Some(vec![&s.env, 40, 0]));
+    assert_eq!(s.contract.try_claim(&id, &b), Err(Ok(Error::NothingToClaim)));
+    assert_eq!(s.contract.claim(&id, &a), 40);
+
+    // with nobody on the side that would be paid, the market is cancelled
+    let id = s.contract.create_scalar_market(&s.admin, &s.token.address, &100, &0, &10);
+    s.contract.record_votes(&id, &a, &LONG, &10);
+    s.pass(101);
+    s.contract.close_scalar(&id, &s.admin, &0);
+    assert_eq!(s.contract.view_predictions(&id).status, MarketStatus::Cancelled);
+    assert_eq!(s.contract.refund(&id, &a), 10);
+}
+
+#[test]
+fn close_split() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    let b = s.staker(100);
+    let c = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &60);
+    s.contract.record_votes(&0, &b, &1, &40);
+    s.contract.record_votes(&0, &c, &0, &20);
+
+    s.pass(101);
+    // a tie, the pool is halved between the outcomes
+    s.contract.close_split(&0, &s.admin, &vec![&s.env, 5000, 5000]);
+    assert_eq!(s.contract.view_split(&0), Some(vec![&s.env, 60, 60]));
+    assert_eq!(s.contract.view_claimable(&0, &a), 45);
+    assert_eq!(s.contract.claim(&0, &a), 45);
+    assert_eq!(s.contract.claim(&0, &b), 60);
+    assert_eq!(s.contract.claim(&0, &c), 15);
+    assert_eq!(s.contract.try_claim(&0, &c), Err(Ok(Error::AlreadyClaimed)));
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+#[test]
+fn close_split_checks() {
+    let s = Setup::new();
+    let a = s.staker(100);
+    s.contract.record_votes(&0, &a, &0, &10);
+
+    assert_eq!(s.contract.try_close_split(&0, &s.admin, &vec![&s.env, 5000, 5000]), Err(Ok(Error::MarketRunning)));
+    s.pass(101);
+    assert_eq!(s.contract.try_close_split(&0, &s.admin, &vec![&s.env, 5000, 4999]), Err(Ok(Error::InvalidWeights)));
+    assert_eq!(s.contract.try_close_split(&0, &s.admin, &vec![&s.env, 10_000]), Err(Ok(Error::InvalidWeights)));
+    // weights that would wrap around a u32 back to 10000
+    assert_eq!(s.contract.try_close_split(&0, &s.admin, &vec![&s.env, u32::MAX, 10_001]), Err(Ok(Error::InvalidWeights)));
+    assert_eq!(s.contract.try_close_split(&0, &a, &vec![&s.env, 5000, 5000]), Err(Ok(Error::AdminInvalid)));
+    // nobody staked on the outcome given the whole pool, so the market is cancelled
+    s.contract.close_split(&0, &s.admin, &vec![&s.env, 0, 10_000]);
+    assert_eq!(s.contract.view_predictions(&0).status, MarketStatus::Cancelled);
+    assert_eq!(s.contract.refund(&0, &a), 10);
+
+    let id = s.contract.create_market(&s.admin, &s.token.address, &100, &outcomes(&s.env), &None);
+    let config = DisputeConfig { arbiter: s.admin.clone(), window: 10, bond: 5 };
+    s.contract.set_dispute(&id, &s.admin, &config);
+    s.pass(101);
+    assert_eq!(s.contract.try_close_split(&id, &s.admin, &vec![&s.env, 5000, 5000]), Err(Ok(Error::DisputeMarket)));
+}
+
+#[test]
+fn lmsr_close_split() {
+    let s = Setup::new();
+    s.token_admin.mint(&s.admin, &1000);
+    let a = s.staker(1000);
+    let b = s.staker(1000);
+    s.contract.set_lmsr(&0, &s.admin, &1000);
+    let paid = s.contract.buy_shares(&0, &a, &0, &100, &100) + s.contract.buy_shares(&0, &b, &1, &100, &100);
+
+    s.pass(101);
+    s.contract.close_split(&0, &s.admin, &vec![&s.env, 2500, 7500]);
+    // each share redeems for its outcome's weight
+    assert_eq!(s.contract.claim(&0, &a), 25);
+    assert_eq!(s.contract.claim(&0, &b), 75);
+    assert_eq!(s.contract.reclaim_subsidy(&0, &s.admin), 695 + paid - 100);
+    assert_eq!(s.token.balance(&s.contract.address), 0);
+}
+
+// random sequences of stakes, time passing, closes and claims, see harness.rs
+// for the invariants checked along the way
+mod payout_invariants {
+    use crate::harness::{self, Step, OUTCOMES, USERS};
+    use proptest::prelude::*;
+
+    fn step() -> impl Strategy<Value = Step> {
+        let user = 0..USERS as u8;
+        // one past the last outcome so invalid outcomes are tried too
+        let outcome = 0..=OUTCOMES;
+        prop_oneof![
+            6 => (user.clone(), outcome.clone(), 0..1_000_000u32)
+                .prop_map(|(user, outcome, votes)| Step::Stake { user, outcome, votes }),
+            2 => (0..80u16).prop_map(|secs| Step::Pass { secs }),
+            1 => Just(Step::Lock),
+            2 => outcome.prop_map(|winner| Step::Close { winner }),
+            1 => Just(Step::Cancel),
+            3 => user.clone().prop_map(|user| Step::Claim { user }),
+            1 => user.prop_map(|user| Step::Refund { user }),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn payouts_hold_invariants(steps in proptest::collection::vec(step(), 1..40)) {
+            harness::run(&steps);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use soroban_sdk::{Address, BytesN, contractimpl, Env};
+
+use crate::{events, Error, Registry, VoteContract, VoteContractClient};
+
+// version of the storage layout this code reads and writes, stored under
+// Registry::Version. bump it whenever the layout changes and add a step to
+// migrate() converting storage from the version before
+// 1: many markets keyed by market id, kept in persistent storage. the first
+// version that can be upgraded, so there is nothing older to migrate from
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[contractimpl]
+impl VoteContract {
+    // function for the contract admin set by init() to replace the contract's code
+    // with the uploaded wasm at new_wasm_hash, market admins can't
+    // storage is kept as is, call migrate() afterwards if SCHEMA_VERSION changed
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        Self::check_contract_admin(&env, &admin)?;
+        env.deployer().update_current_contract_wasm(new_wasm_hash.clone());
+
+        events::upgrade(&env, &admin, &new_wasm_hash);
+        Ok(())
+    }
+
+    // function to get the schema version the contract's storage is in, a contract
+    // with nothing stored yet is on the current one
+    pub fn view_version(env: Env) -> u32 {
+        env.storage().instance().get(&Registry::Version).unwrap_or(SCHEMA_VERSION)
+    }
+
+    // function anyone can call after an upgrade to bring storage up to SCHEMA_VERSION,
+    // markets can't be used until it has run. does nothing if storage is already
+    // current. returns the schema version storage is now in
+    pub fn migrate(env: Env) -> Result<u32, Error> {
+        let version = Self::view_version(env.clone());
+        if version == SCHEMA_VERSION {
+            return Ok(version);
+        }
+        // no layout has changed since version 1 yet, steps for later versions go here
+        env.storage().instance().set(&Registry::Version, &SCHEMA_VERSION);
+
+        events::migrate(&env, version, SCHEMA_VERSION);
+        Ok(SCHEMA_VERSION)
+    }
+}